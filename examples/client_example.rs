@@ -27,7 +27,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     for (name, duration, priority) in tasks {
         let response = client
-            .post(&format!("{}/api/tasks", BASE_URL))
+            .post(format!("{}/api/tasks", BASE_URL))
             .json(&json!({
                 "name": name,
                 "duration_ms": duration,
@@ -46,7 +46,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("\n📊 Obtendo estatísticas...");
     let stats_response = client
-        .get(&format!("{}/api/stats", BASE_URL))
+        .get(format!("{}/api/stats", BASE_URL))
         .send()
         .await?;
     
@@ -62,7 +62,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("\n📋 Listando todas as tarefas...");
     let list_response = client
-        .get(&format!("{}/api/tasks", BASE_URL))
+        .get(format!("{}/api/tasks", BASE_URL))
         .send()
         .await?;
 
@@ -0,0 +1,249 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::handlers::{check_task_request, FilterParams};
+use super::{api_error, apply, owned_task, ApiError};
+use crate::auth::Tenant;
+use crate::models::{CreateTaskRequest, TaskFilter, TaskQuery};
+use crate::processor::Submission;
+use crate::AppState;
+
+pub const MAX_BATCH_SIZE: usize = 10_000;
+// Limite do corpo de `POST /api/tasks/batch`, acima do padrão do axum
+pub const MAX_BATCH_BYTES: usize = 16 * 1024 * 1024;
+
+// Seleção das operações em lote: lista de ids ou filtro da listagem (`{}` seleciona tudo)
+#[derive(Debug, Deserialize)]
+pub struct TaskSelection {
+    pub ids: Option<Vec<Uuid>>,
+    pub filter: Option<FilterParams>,
+}
+
+enum Selected {
+    Ids(Vec<Uuid>),
+    Filter(TaskFilter),
+}
+
+impl TaskSelection {
+    // O filtro fica restrito ao tenant; ids de outros tenants falham como inexistentes
+    fn resolve(self, tenant: &Tenant) -> Result<Selected, ApiError> {
+        match (self.ids, self.filter) {
+            (Some(ids), None) => Ok(Selected::Ids(ids)),
+            (None, Some(filter)) => filter
+                .into_filter()
+                .map(|filter| {
+                    Selected::Filter(TaskFilter {
+                        tenant: Some(tenant.0.clone()),
+                        ..filter
+                    })
+                })
+                .map_err(|e| api_error(StatusCode::BAD_REQUEST, e)),
+            _ => Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "Informe apenas um entre ids e filter",
+            )),
+        }
+    }
+}
+
+// Corpo: array JSON de tarefas ou NDJSON (`Content-Type: application/x-ndjson`),
+// uma tarefa por linha. A resposta traz o resultado de cada item, na ordem do envio.
+pub async fn create_batch(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let ndjson = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("ndjson"));
+    let items = if ndjson {
+        parse_ndjson(&body)
+    } else {
+        parse_array(&body)?
+    };
+    if items.is_empty() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "Lote vazio"));
+    }
+    if items.len() > MAX_BATCH_SIZE {
+        return Err(api_error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Lote excede o limite de {} tarefas", MAX_BATCH_SIZE),
+        ));
+    }
+
+    let mut results: Vec<Option<Result<Submission, String>>> = Vec::with_capacity(items.len());
+    let mut accepted = Vec::new();
+    let mut requests = Vec::new();
+    for (i, item) in items.into_iter().enumerate() {
+        match item.and_then(|request| check_task_request(&request).map(|_| request)) {
+            Ok(mut request) => {
+                request.tenant = tenant.clone();
+                accepted.push(i);
+                requests.push(request);
+                results.push(None);
+            }
+            Err(e) => results.push(Some(Err(e))),
+        }
+    }
+    for (i, result) in accepted
+        .into_iter()
+        .zip(state.processor.submit_tasks(requests).await)
+    {
+        results[i] = Some(result);
+    }
+
+    let (mut created, mut existing, mut failed) = (0, 0, 0);
+    let results: Vec<serde_json::Value> = results
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, result)| match result {
+            Ok(Submission::Created(task)) => {
+                created += 1;
+                serde_json::json!({ "index": index, "status": "created", "task": task })
+            }
+            Ok(Submission::Existing(task)) => {
+                existing += 1;
+                serde_json::json!({ "index": index, "status": "existing", "task": task })
+            }
+            Err(e) => {
+                failed += 1;
+                serde_json::json!({ "index": index, "status": "error", "error": e })
+            }
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "created": created,
+        "existing": existing,
+        "failed": failed,
+        "results": results
+    })))
+}
+
+// Um item malformado não invalida o lote: o erro fica no resultado do item
+fn parse_array(body: &[u8]) -> Result<Vec<Result<CreateTaskRequest, String>>, ApiError> {
+    let values: Vec<serde_json::Value> = serde_json::from_slice(body).map_err(|e| {
+        api_error(
+            StatusCode::BAD_REQUEST,
+            format!("Esperado um array JSON de tarefas: {}", e),
+        )
+    })?;
+    Ok(values
+        .into_iter()
+        .map(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+        .collect())
+}
+
+fn parse_ndjson(body: &[u8]) -> Vec<Result<CreateTaskRequest, String>> {
+    body.split(|&byte| byte == b'\n')
+        .filter(|line| !line.trim_ascii().is_empty())
+        .map(|line| serde_json::from_slice(line).map_err(|e| e.to_string()))
+        .collect()
+}
+
+pub async fn cancel_many(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Json(selection): Json<TaskSelection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let ids = match selection.resolve(&tenant)? {
+        Selected::Ids(ids) => ids,
+        Selected::Filter(filter) => select_tasks(&state, filter).await,
+    };
+
+    let mut cancelled = Vec::new();
+    let mut errors = serde_json::Map::new();
+    for id in ids {
+        let result = match owned_task(&state, &tenant, id) {
+            Some(_) => state.processor.cancel_task(id).await,
+            None => Err("Tarefa não encontrada".to_string()),
+        };
+        match result {
+            Ok(()) => cancelled.push(id),
+            Err(e) => {
+                errors.insert(id.to_string(), e.into());
+            }
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "cancelled": cancelled,
+        "errors": errors
+    })))
+}
+
+// Devolve à fila tarefas da DLQ; com `filter`, a busca é feita na DLQ
+pub async fn requeue_many(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Json(selection): Json<TaskSelection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let ids = match selection.resolve(&tenant)? {
+        Selected::Ids(ids) => ids,
+        Selected::Filter(filter) => state
+            .processor
+            .find_dead_letters(&filter)
+            .into_iter()
+            .map(|task| task.id)
+            .collect(),
+    };
+    let (done, errors) = apply(ids, |id| {
+        owned_task(&state, &tenant, id).ok_or("Tarefa não encontrada")?;
+        state.processor.requeue_dead_letter(id).map(|_| ())
+    });
+
+    Ok(Json(serde_json::json!({
+        "requeued": done,
+        "errors": errors
+    })))
+}
+
+// Remove tarefas finalizadas, da coleção principal e da DLQ
+pub async fn delete_many(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Json(selection): Json<TaskSelection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let ids = match selection.resolve(&tenant)? {
+        Selected::Ids(ids) => ids,
+        Selected::Filter(filter) => {
+            let dead_letters = state.processor.find_dead_letters(&filter);
+            let mut ids = select_tasks(&state, filter).await;
+            ids.extend(dead_letters.into_iter().map(|task| task.id));
+            ids
+        }
+    };
+    let (done, errors) = apply(ids, |id| {
+        owned_task(&state, &tenant, id).ok_or("Tarefa não encontrada")?;
+        state.processor.delete_task(id).map(|_| ())
+    });
+
+    Ok(Json(serde_json::json!({
+        "deleted": done,
+        "errors": errors
+    })))
+}
+
+async fn select_tasks(state: &AppState, filter: TaskFilter) -> Vec<Uuid> {
+    let query = TaskQuery {
+        filter,
+        ..Default::default()
+    };
+    state
+        .processor
+        .list_tasks(&query)
+        .await
+        .tasks
+        .into_iter()
+        .map(|task| task.id)
+        .collect()
+}
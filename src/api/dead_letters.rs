@@ -0,0 +1,194 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::{api_error, apply, ApiError};
+use crate::auth::Tenant;
+use crate::models::Task;
+use crate::AppState;
+
+const NOT_FOUND: &str = "Tarefa não encontrada na fila de mensagens mortas";
+
+// Tarefa da DLQ pertencente ao tenant
+fn owned_dead_letter(state: &AppState, tenant: &Tenant, id: Uuid) -> Result<Task, String> {
+    state
+        .processor
+        .get_dead_letter(id)
+        .filter(|task| tenant.owns(task))
+        .ok_or_else(|| NOT_FOUND.to_string())
+}
+
+// Corpo das operações em lote: lista de ids ou `{"all": true}` para toda a DLQ do
+// tenant. Um corpo inválido é recusado em vez de selecionar tudo.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeadLetterSelection {
+    pub ids: Option<Vec<Uuid>>,
+    #[serde(default)]
+    pub all: bool,
+}
+
+impl DeadLetterSelection {
+    fn resolve(self, state: &AppState, tenant: &Tenant) -> Result<Vec<Uuid>, ApiError> {
+        match (self.ids, self.all) {
+            (Some(ids), false) => Ok(ids),
+            (None, true) => Ok(state
+                .processor
+                .list_dead_letters()
+                .into_iter()
+                .filter(|task| tenant.owns(task))
+                .map(|task| task.id)
+                .collect()),
+            _ => Err(api_error(
+                StatusCode::BAD_REQUEST,
+                "Informe apenas um entre ids e all",
+            )),
+        }
+    }
+}
+
+pub async fn list_dead_letters(State(state): State<AppState>, tenant: Tenant) -> Json<Vec<Task>> {
+    let mut tasks = state.processor.list_dead_letters();
+    tasks.retain(|task| tenant.owns(task));
+    Json(tasks)
+}
+
+pub async fn get_dead_letter(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Task>, ApiError> {
+    owned_dead_letter(&state, &tenant, id)
+        .map(Json)
+        .map_err(|e| api_error(StatusCode::NOT_FOUND, e))
+}
+
+// A devolução não passa pela cota de pendentes do tenant
+pub async fn requeue_dead_letter(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Task>, ApiError> {
+    owned_dead_letter(&state, &tenant, id)
+        .and_then(|_| state.processor.requeue_dead_letter(id))
+        .map(Json)
+        .map_err(|e| api_error(StatusCode::NOT_FOUND, e))
+}
+
+pub async fn purge_dead_letter(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    owned_dead_letter(&state, &tenant, id)
+        .and_then(|_| state.processor.purge_dead_letter(id))
+        .map_err(|e| api_error(StatusCode::NOT_FOUND, e))?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Tarefa removida da fila de mensagens mortas",
+        "task_id": id
+    })))
+}
+
+pub async fn requeue_many(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Json(selection): Json<DeadLetterSelection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let ids = selection.resolve(&state, &tenant)?;
+    let (done, errors) = apply(ids, |id| {
+        owned_dead_letter(&state, &tenant, id)?;
+        state.processor.requeue_dead_letter(id).map(|_| ())
+    });
+
+    Ok(Json(serde_json::json!({
+        "requeued": done,
+        "errors": errors
+    })))
+}
+
+pub async fn purge_many(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Json(selection): Json<DeadLetterSelection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let ids = selection.resolve(&state, &tenant)?;
+    let (done, errors) = apply(ids, |id| {
+        owned_dead_letter(&state, &tenant, id)?;
+        state.processor.purge_dead_letter(id).map(|_| ())
+    });
+
+    Ok(Json(serde_json::json!({
+        "purged": done,
+        "errors": errors
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::models::{CreateTaskRequest, TaskPriority};
+    use crate::processor::TaskProcessor;
+    use crate::registry::HandlerRegistry;
+    use crate::scheduler::Scheduler;
+    use crate::store::{MemoryStore, TaskStore};
+    use axum::{body::Body, http::Request, routing::post, Router};
+    use std::sync::Arc;
+    use tower::Service;
+
+    #[tokio::test]
+    async fn test_malformed_selection_purges_nothing() {
+        let dead_letters: Arc<dyn TaskStore> = Arc::new(MemoryStore::new());
+        let mut failed = Task::from_request(CreateTaskRequest::new("Falha", 0, TaskPriority::Low));
+        failed.mark_as_processing();
+        failed.mark_as_failed("erro".to_string());
+        dead_letters.insert(failed).unwrap();
+        let processor = Arc::new(TaskProcessor::with_store(
+            Default::default(),
+            HandlerRegistry::with_builtins(),
+            Arc::new(MemoryStore::new()),
+            dead_letters,
+        ));
+        let state = AppState {
+            scheduler: Arc::new(Scheduler::open(processor.clone(), None).unwrap()),
+            processor: processor.clone(),
+            metrics: Arc::new(Metrics::new()),
+        };
+        let app = Router::new()
+            .route("/api/dead-letters/purge", post(purge_many))
+            .with_state(state);
+        let status = |body: &'static str, json: bool| {
+            let mut request = Request::post("/api/dead-letters/purge");
+            if json {
+                request = request.header("content-type", "application/json");
+            }
+            let mut app = app.clone();
+            async move {
+                app.call(request.body(Body::from(body)).unwrap())
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        for (body, json) in [
+            ("", false),
+            (r#"{"all": true}"#, false),
+            ("{ids", true),
+            (r#"{"id": []}"#, true),
+            ("{}", true),
+            (r#"{"ids": [], "all": true}"#, true),
+        ] {
+            assert!(status(body, json).await.is_client_error(), "{}", body);
+            assert_eq!(processor.list_dead_letters().len(), 1, "{}", body);
+        }
+
+        assert_eq!(status(r#"{"all": true}"#, true).await, StatusCode::OK);
+        assert!(processor.list_dead_letters().is_empty());
+    }
+}
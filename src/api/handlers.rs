@@ -1,11 +1,19 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
     response::Json,
 };
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
 use uuid::Uuid;
 
-use crate::models::{CreateTaskRequest, Task, TaskStats};
+use super::{api_error, owned_task, ApiError};
+use crate::auth::Tenant;
+use crate::models::{
+    CreateTaskRequest, Cursor, SortField, Task, TaskFilter, TaskQuery, TaskResult, TaskStats,
+};
+use crate::processor::{Submission, SubmitError};
+use crate::registry::DEFAULT_KIND;
 use crate::AppState;
 
 pub async fn health_check() -> Json<serde_json::Value> {
@@ -16,42 +24,69 @@ pub async fn health_check() -> Json<serde_json::Value> {
     }))
 }
 
+// Com `Idempotency-Key` (cabeçalho ou campo `idempotency_key`), um reenvio dentro da
+// janela devolve a tarefa original, marcada pelo cabeçalho `Idempotent-Replayed`.
+// A tarefa pertence ao tenant da credencial; além da cota de pendentes, responde 429.
 pub async fn create_task(
     State(state): State<AppState>,
-    Json(request): Json<CreateTaskRequest>,
-) -> Result<Json<Task>, (StatusCode, Json<serde_json::Value>)> {
-    if request.name.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": "Nome da tarefa não pode estar vazio"
-            })),
-        ));
+    Tenant(tenant): Tenant,
+    headers: HeaderMap,
+    Json(mut request): Json<CreateTaskRequest>,
+) -> Result<(HeaderMap, Json<Task>), (StatusCode, Json<serde_json::Value>)> {
+    if let Some(value) = headers.get("idempotency-key") {
+        let key = value.to_str().map_err(|_| {
+            api_error(
+                StatusCode::BAD_REQUEST,
+                "Idempotency-Key deve conter apenas ASCII visível",
+            )
+        })?;
+        request.idempotency_key = Some(key.to_string());
     }
+    request.tenant = tenant;
+    validate_task_request(&request)?;
 
-    if request.duration_ms == 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": "Duração deve ser maior que zero"
-            })),
-        ));
+    match state.processor.submit_task(request).await {
+        Ok(Submission::Created(task)) => Ok((HeaderMap::new(), Json(task))),
+        Ok(Submission::Existing(task)) => {
+            let mut headers = HeaderMap::new();
+            headers.insert("idempotent-replayed", HeaderValue::from_static("true"));
+            Ok((headers, Json(task)))
+        }
+        Err(e) => Err(submit_error(e)),
+    }
+}
+
+pub(super) fn submit_error(e: SubmitError) -> ApiError {
+    match e {
+        SubmitError::Invalid(e) => api_error(StatusCode::BAD_REQUEST, e),
+        SubmitError::QuotaExceeded(e) => api_error(StatusCode::TOO_MANY_REQUESTS, e),
+    }
+}
+
+// Regras de entrada da API, compartilhadas com os modelos de tarefa dos agendamentos
+pub(super) fn validate_task_request(request: &CreateTaskRequest) -> Result<(), ApiError> {
+    check_task_request(request).map_err(|e| api_error(StatusCode::BAD_REQUEST, e))
+}
+
+// Mesmas regras, com o erro por item usado na criação em lote
+pub(super) fn check_task_request(request: &CreateTaskRequest) -> Result<(), String> {
+    if request.name.is_empty() {
+        return Err("Nome da tarefa não pode estar vazio".to_string());
     }
 
-    let task = state.processor.create_task(
-        request.name,
-        request.duration_ms,
-        request.priority,
-    ).await;
+    if request.kind == DEFAULT_KIND && request.duration_ms == 0 {
+        return Err("Duração deve ser maior que zero".to_string());
+    }
 
-    Ok(Json(task))
+    Ok(())
 }
 
 pub async fn get_task(
     State(state): State<AppState>,
+    tenant: Tenant,
     Path(id): Path<Uuid>,
 ) -> Result<Json<Task>, (StatusCode, Json<serde_json::Value>)> {
-    match state.processor.get_task(id).await {
+    match owned_task(&state, &tenant, id) {
         Some(task) => Ok(Json(task)),
         None => Err((
             StatusCode::NOT_FOUND,
@@ -62,17 +97,220 @@ pub async fn get_task(
     }
 }
 
+pub const LOGS_MAX_LIMIT: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct LogsQuery {
+    // Cursor: `seq` da última linha já lida
+    pub after: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+// Log escrito pelos handlers da tarefa; `next_after` continua a leitura de onde parou
+pub async fn get_task_logs(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+    Query(query): Query<LogsQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    owned_task(&state, &tenant, id)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Tarefa não encontrada"))?;
+    let limit = query.limit.unwrap_or(LOGS_MAX_LIMIT).clamp(1, LOGS_MAX_LIMIT);
+    let lines = state
+        .processor
+        .task_logs(id, query.after, limit)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Tarefa não encontrada"))?;
+    let next_after = lines.last().map(|line| line.seq).or(query.after);
+
+    Ok(Json(serde_json::json!({
+        "task_id": id,
+        "lines": lines,
+        "next_after": next_after
+    })))
+}
+
+// Espera padrão e máxima do long-poll de resultado
+const RESULT_WAIT_MS: u64 = 30_000;
+const RESULT_MAX_WAIT_MS: u64 = 120_000;
+
+#[derive(Debug, Deserialize)]
+pub struct ResultQuery {
+    pub wait_ms: Option<u64>,
+}
+
+// Long-poll: responde 200 quando a tarefa termina ou 202 se o prazo de espera acabar antes
+pub async fn get_task_result(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ResultQuery>,
+) -> Result<(StatusCode, Json<TaskResult>), ApiError> {
+    owned_task(&state, &tenant, id)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Tarefa não encontrada"))?;
+    let wait = query
+        .wait_ms
+        .unwrap_or(RESULT_WAIT_MS)
+        .min(RESULT_MAX_WAIT_MS);
+    let task = state
+        .processor
+        .wait_for_terminal(id, tokio::time::Duration::from_millis(wait))
+        .await
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Tarefa não encontrada"))?;
+
+    let status = if task.status.is_terminal() {
+        StatusCode::OK
+    } else {
+        StatusCode::ACCEPTED
+    };
+    let result = state
+        .processor
+        .task_result(task)
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok((status, Json(result)))
+}
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1_000;
+
+// Filtros da listagem, também aceitos no corpo das operações em lote
+#[derive(Debug, Default, Deserialize)]
+pub struct FilterParams {
+    // Listas separadas por vírgula, ex.: `?status=pending,retrying&priority=high`
+    pub status: Option<String>,
+    pub priority: Option<String>,
+    pub name: Option<String>,
+    // Ex.: `?labels=env:prod,team:dados`
+    pub labels: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub completed_after: Option<DateTime<Utc>>,
+    pub completed_before: Option<DateTime<Utc>>,
+}
+
+impl FilterParams {
+    pub(super) fn into_filter(self) -> Result<TaskFilter, String> {
+        Ok(TaskFilter {
+            tenant: None,
+            workflow_id: None,
+            statuses: self
+                .status
+                .as_deref()
+                .map(TaskFilter::parse_list)
+                .transpose()?
+                .unwrap_or_default(),
+            priorities: self
+                .priority
+                .as_deref()
+                .map(TaskFilter::parse_list)
+                .transpose()?
+                .unwrap_or_default(),
+            name: self.name.filter(|name| !name.trim().is_empty()),
+            labels: self
+                .labels
+                .as_deref()
+                .map(TaskFilter::parse_labels)
+                .unwrap_or_default(),
+            created_after: self.created_after,
+            created_before: self.created_before,
+            completed_after: self.completed_after,
+            completed_before: self.completed_before,
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    #[serde(flatten)]
+    pub filter: FilterParams,
+    // `created_at` (padrão), `completed_at`, `priority` ou `name`
+    pub sort: Option<String>,
+    pub order: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl ListQuery {
+    fn into_query(self) -> Result<TaskQuery, String> {
+        let sort: SortField = self
+            .sort
+            .as_deref()
+            .map(str::parse)
+            .transpose()?
+            .unwrap_or_default();
+        let order = match self.order.as_deref() {
+            Some(order) => order.parse()?,
+            None => sort.default_order(),
+        };
+        let after = self.cursor.as_deref().map(Cursor::decode).transpose()?;
+        if after.as_ref().is_some_and(|cursor| cursor.sort() != sort) {
+            return Err("Cursor gerado com outra ordenação".to_string());
+        }
+
+        Ok(TaskQuery {
+            filter: self.filter.into_filter()?,
+            sort,
+            order,
+            after,
+            limit: Some(self.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE)),
+        })
+    }
+}
+
+// O corpo continua sendo a lista de tarefas; o total e o cursor da próxima
+// página vão nos cabeçalhos `X-Total-Count` e `X-Next-Cursor`
 pub async fn list_tasks(
     State(state): State<AppState>,
-) -> Json<Vec<Task>> {
-    let tasks = state.processor.list_tasks().await;
-    Json(tasks)
+    Tenant(tenant): Tenant,
+    Query(query): Query<ListQuery>,
+) -> Result<(HeaderMap, Json<Vec<Task>>), ApiError> {
+    let mut query = query
+        .into_query()
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+    query.filter.tenant = Some(tenant);
+    let page = state.processor.list_tasks(&query).await;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(page.total));
+    if let Some(cursor) = page.next_cursor {
+        if let Ok(value) = HeaderValue::from_str(&cursor.encode()) {
+            headers.insert("x-next-cursor", value);
+        }
+    }
+    Ok((headers, Json(page.tasks)))
+}
+
+// Só tarefas finalizadas podem ser removidas; as demais precisam ser canceladas antes
+pub async fn delete_task(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let task = owned_task(&state, &tenant, id)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Tarefa não encontrada"))?;
+    if !task.status.is_terminal() {
+        return Err(api_error(
+            StatusCode::CONFLICT,
+            "Tarefa ainda não foi finalizada; cancele-a antes",
+        ));
+    }
+
+    state
+        .processor
+        .delete_task(id)
+        .map_err(|e| api_error(StatusCode::NOT_FOUND, e))?;
+    Ok(Json(serde_json::json!({
+        "message": "Tarefa removida",
+        "task_id": id
+    })))
 }
 
 pub async fn cancel_task(
     State(state): State<AppState>,
+    tenant: Tenant,
     Path(id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    owned_task(&state, &tenant, id)
+        .ok_or_else(|| api_error(StatusCode::BAD_REQUEST, "Tarefa não encontrada"))?;
     match state.processor.cancel_task(id).await {
         Ok(_) => Ok(Json(serde_json::json!({
             "message": "Tarefa cancelada com sucesso",
@@ -87,10 +325,23 @@ pub async fn cancel_task(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    // Janela deslizante das latências: 1m, 5m ou 1h (padrão: desde o início)
+    pub window: Option<String>,
+}
+
+// Estatísticas do tenant da credencial, com o uso das suas cotas
 pub async fn get_stats(
     State(state): State<AppState>,
-) -> Json<TaskStats> {
-    let stats = state.processor.get_stats();
-    Json(stats)
+    Tenant(tenant): Tenant,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<TaskStats>, ApiError> {
+    let window = query
+        .window
+        .map(|window| window.parse())
+        .transpose()
+        .map_err(|e: String| api_error(StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(state.processor.tenant_stats(&tenant, window)))
 }
 
@@ -1,2 +1,50 @@
+pub mod bulk;
+pub mod dead_letters;
 pub mod handlers;
+pub mod schedules;
+pub mod workflows;
 
+use axum::{http::StatusCode, response::Json};
+use uuid::Uuid;
+
+use crate::auth::Tenant;
+use crate::models::Task;
+use crate::AppState;
+
+pub type ApiError = (StatusCode, Json<serde_json::Value>);
+
+pub fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (
+        status,
+        Json(serde_json::json!({
+            "error": message.into()
+        })),
+    )
+}
+
+// Tarefa do tenant, na coleção principal ou na DLQ; as de outros tenants não
+// existem para ele
+pub(crate) fn owned_task(state: &AppState, tenant: &Tenant, id: Uuid) -> Option<Task> {
+    state
+        .processor
+        .find_task(id)
+        .filter(|task| tenant.owns(task))
+}
+
+// Aplica a operação a cada id; retorna os ids concluídos e os erros por id
+pub(crate) fn apply(
+    ids: Vec<Uuid>,
+    mut op: impl FnMut(Uuid) -> Result<(), String>,
+) -> (Vec<Uuid>, serde_json::Map<String, serde_json::Value>) {
+    let mut done = Vec::new();
+    let mut errors = serde_json::Map::new();
+    for id in ids {
+        match op(id) {
+            Ok(()) => done.push(id),
+            Err(e) => {
+                errors.insert(id.to_string(), e.into());
+            }
+        }
+    }
+    (done, errors)
+}
@@ -0,0 +1,159 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::handlers::validate_task_request;
+use super::{api_error, ApiError};
+use crate::auth::Tenant;
+use crate::models::{CreateScheduleRequest, Schedule, TaskStatus};
+use crate::AppState;
+
+const NOT_FOUND: &str = "Agendamento não encontrado";
+
+// Agendamento com o status atual da última tarefa criada por ele
+#[derive(Debug, Serialize)]
+pub struct ScheduleResponse {
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    pub last_task_status: Option<TaskStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewQuery {
+    #[serde(default = "default_preview_count")]
+    pub count: usize,
+}
+
+fn default_preview_count() -> usize {
+    5
+}
+
+async fn respond(state: &AppState, schedule: Schedule) -> ScheduleResponse {
+    let last_task_status = match schedule.last_run.as_ref().and_then(|run| run.task_id) {
+        Some(id) => state.processor.get_task(id).await.map(|task| task.status),
+        None => None,
+    };
+    ScheduleResponse {
+        schedule,
+        last_task_status,
+    }
+}
+
+fn found(schedule: Result<Option<Schedule>, String>) -> Result<Schedule, ApiError> {
+    schedule
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, NOT_FOUND))
+}
+
+// Agendamentos pertencem ao tenant das tarefas que criam
+fn owned(state: &AppState, tenant: &Tenant, id: Uuid) -> Result<Schedule, ApiError> {
+    state
+        .scheduler
+        .get(id)
+        .filter(|schedule| schedule.task.tenant == tenant.0)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, NOT_FOUND))
+}
+
+pub async fn create_schedule(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+    Json(mut request): Json<CreateScheduleRequest>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    request.task.tenant = tenant;
+    validate_task_request(&request.task)?;
+    let schedule = state
+        .scheduler
+        .create(request)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(respond(&state, schedule).await))
+}
+
+pub async fn list_schedules(
+    State(state): State<AppState>,
+    tenant: Tenant,
+) -> Json<Vec<ScheduleResponse>> {
+    let mut schedules = Vec::new();
+    for schedule in state
+        .scheduler
+        .list()
+        .into_iter()
+        .filter(|schedule| schedule.task.tenant == tenant.0)
+    {
+        schedules.push(respond(&state, schedule).await);
+    }
+    Json(schedules)
+}
+
+pub async fn get_schedule(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    let schedule = owned(&state, &tenant, id)?;
+    Ok(Json(respond(&state, schedule).await))
+}
+
+pub async fn update_schedule(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+    Json(mut request): Json<CreateScheduleRequest>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    owned(&state, &tenant, id)?;
+    request.task.tenant = tenant.0;
+    validate_task_request(&request.task)?;
+    let schedule = found(state.scheduler.update(id, request))?;
+    Ok(Json(respond(&state, schedule).await))
+}
+
+pub async fn delete_schedule(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    owned(&state, &tenant, id)?;
+    found(state.scheduler.delete(id))?;
+    Ok(Json(serde_json::json!({
+        "message": "Agendamento removido com sucesso",
+        "schedule_id": id
+    })))
+}
+
+pub async fn pause_schedule(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    owned(&state, &tenant, id)?;
+    let schedule = found(state.scheduler.pause(id))?;
+    Ok(Json(respond(&state, schedule).await))
+}
+
+pub async fn resume_schedule(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    owned(&state, &tenant, id)?;
+    let schedule = found(state.scheduler.resume(id))?;
+    Ok(Json(respond(&state, schedule).await))
+}
+
+pub async fn preview_schedule(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+    Query(query): Query<PreviewQuery>,
+) -> Result<Json<Vec<DateTime<Utc>>>, ApiError> {
+    owned(&state, &tenant, id)?;
+    state
+        .scheduler
+        .preview(id, query.count)
+        .map(Json)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, NOT_FOUND))
+}
@@ -0,0 +1,41 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use uuid::Uuid;
+
+use super::handlers::{submit_error, validate_task_request};
+use super::{api_error, ApiError};
+use crate::auth::Tenant;
+use crate::models::{CreateWorkflowRequest, Workflow};
+use crate::AppState;
+
+pub async fn create_workflow(
+    State(state): State<AppState>,
+    Tenant(tenant): Tenant,
+    Json(request): Json<CreateWorkflowRequest>,
+) -> Result<Json<Workflow>, ApiError> {
+    for item in &request.tasks {
+        validate_task_request(&item.task)?;
+    }
+
+    state
+        .processor
+        .create_workflow(&tenant, request)
+        .map(Json)
+        .map_err(submit_error)
+}
+
+pub async fn get_workflow(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Workflow>, ApiError> {
+    state
+        .processor
+        .get_workflow(id)
+        .filter(|workflow| workflow.tasks.iter().all(|task| tenant.owns(task)))
+        .map(Json)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Workflow não encontrado"))
+}
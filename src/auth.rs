@@ -0,0 +1,371 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query, Request, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use crate::api::api_error;
+use crate::config::AuthConfig;
+use crate::models::{Task, DEFAULT_TENANT};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    ApiKey,
+    Jwt,
+}
+
+// Quem fez a requisição; fica nas extensões da requisição autenticada
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub subject: String,
+    // Dono das tarefas criadas com a credencial: o nome da chave de API ou o
+    // campo `tenant` do token (na falta dele, o `sub`)
+    pub tenant: String,
+    pub method: AuthMethod,
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: Option<String>,
+    tenant: Option<String>,
+}
+
+// Valida chaves de API e tokens JWT localmente, sem consultar outro serviço
+pub struct Authenticator {
+    api_keys: Vec<(String, String)>,
+    hs256: Option<DecodingKey>,
+    rs256: Option<DecodingKey>,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl Authenticator {
+    pub fn from_config(config: &AuthConfig) -> anyhow::Result<Self> {
+        let rs256 = match &config.jwt_rs256_public_key {
+            Some(path) => {
+                let pem = std::fs::read(path).map_err(|e| {
+                    anyhow::anyhow!("Erro ao ler chave pública RS256 {:?}: {}", path, e)
+                })?;
+                Some(DecodingKey::from_rsa_pem(&pem)?)
+            }
+            None => None,
+        };
+        Ok(Self {
+            api_keys: config.api_keys.clone(),
+            hs256: config
+                .jwt_hs256_secret
+                .as_ref()
+                .map(|secret| DecodingKey::from_secret(secret.as_bytes())),
+            rs256,
+            issuer: config.jwt_issuer.clone(),
+            audience: config.jwt_audience.clone(),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.hs256.is_some() || self.rs256.is_some()
+    }
+
+    pub fn authenticate(&self, token: &str) -> Result<Identity, String> {
+        if let Some((name, _)) = self
+            .api_keys
+            .iter()
+            .find(|(_, key)| constant_time_eq(key.as_bytes(), token.as_bytes()))
+        {
+            return Ok(Identity {
+                subject: name.clone(),
+                tenant: name.clone(),
+                method: AuthMethod::ApiKey,
+            });
+        }
+
+        let header = jsonwebtoken::decode_header(token).map_err(|_| "Credencial inválida")?;
+        let key = match header.alg {
+            Algorithm::HS256 => self.hs256.as_ref(),
+            Algorithm::RS256 => self.rs256.as_ref(),
+            _ => None,
+        }
+        .ok_or_else(|| format!("Algoritmo de token não aceito: {:?}", header.alg))?;
+
+        let mut validation = Validation::new(header.alg);
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        let claims = jsonwebtoken::decode::<Claims>(token, key, &validation)
+            .map_err(|e| format!("Token inválido: {}", e))?
+            .claims;
+        let subject = claims.sub.ok_or("Token sem o campo `sub`")?;
+        let tenant = claims.tenant.unwrap_or_else(|| subject.clone());
+        // O tenant padrão guarda as tarefas criadas com a autenticação desligada
+        if tenant == DEFAULT_TENANT {
+            return Err(format!("Token com o tenant reservado {:?}", DEFAULT_TENANT));
+        }
+        Ok(Identity {
+            tenant,
+            subject,
+            method: AuthMethod::Jwt,
+        })
+    }
+}
+
+// `Authorization: Bearer`, `X-API-Key` ou, só no upgrade do WebSocket (navegadores não
+// enviam cabeçalhos nele), o parâmetro `access_token` da URL
+fn credential(request: &Request) -> Option<String> {
+    let headers: &HeaderMap = request.headers();
+    if let Some(value) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+    {
+        return value
+            .strip_prefix("Bearer ")
+            .map(|token| token.trim().to_string());
+    }
+    if let Some(value) = headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok())
+    {
+        return Some(value.trim().to_string());
+    }
+    if request.uri().path() != "/ws" {
+        return None;
+    }
+    Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()?
+        .0
+        .remove("access_token")
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// Middleware das rotas protegidas; com a autenticação desligada, deixa tudo passar
+pub async fn require_auth(
+    State(auth): State<Arc<Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !auth.is_enabled() {
+        return next.run(request).await;
+    }
+    let result = credential(&request)
+        .ok_or_else(|| "Credencial ausente".to_string())
+        .and_then(|token| auth.authenticate(&token));
+    match result {
+        Ok(identity) => {
+            let span = tracing::Span::current();
+            span.record("auth.subject", identity.subject.as_str());
+            span.record("auth.tenant", identity.tenant.as_str());
+            request.extensions_mut().insert(identity);
+            next.run(request).await
+        }
+        Err(e) => (
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            api_error(StatusCode::UNAUTHORIZED, e),
+        )
+            .into_response(),
+    }
+}
+
+// Tenant de quem fez a requisição; com a autenticação desligada, todos usam o padrão
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tenant(pub String);
+
+impl Tenant {
+    pub fn owns(&self, task: &Task) -> bool {
+        task.tenant == self.0
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Tenant {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Tenant(parts.extensions.get::<Identity>().map_or_else(
+            || DEFAULT_TENANT.to_string(),
+            |identity| identity.tenant.clone(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde::Serialize;
+    use tower::Service;
+
+    const RS256_PRIVATE_KEY: &[u8] = include_bytes!("../tests/fixtures/jwt_rs256.pem");
+
+    #[derive(Serialize)]
+    struct TestClaims<'a> {
+        sub: &'a str,
+        exp: i64,
+        iss: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tenant: Option<&'a str>,
+    }
+
+    fn token(alg: Algorithm, key: &EncodingKey, exp_offset: i64, iss: &str) -> String {
+        claims_token(alg, key, exp_offset, iss, None)
+    }
+
+    fn claims_token(
+        alg: Algorithm,
+        key: &EncodingKey,
+        exp_offset: i64,
+        iss: &str,
+        tenant: Option<&str>,
+    ) -> String {
+        let claims = TestClaims {
+            sub: "usuario-1",
+            exp: chrono::Utc::now().timestamp() + exp_offset,
+            iss,
+            tenant,
+        };
+        jsonwebtoken::encode(&Header::new(alg), &claims, key).unwrap()
+    }
+
+    fn authenticator() -> Authenticator {
+        Authenticator::from_config(&AuthConfig {
+            api_keys: vec![("ci".to_string(), "chave-secreta".to_string())],
+            jwt_hs256_secret: Some("segredo".to_string()),
+            jwt_rs256_public_key: Some("tests/fixtures/jwt_rs256.pub.pem".into()),
+            jwt_issuer: Some("emissor".to_string()),
+            jwt_audience: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_authenticates_api_keys_and_jwt() {
+        let auth = authenticator();
+        assert_eq!(
+            auth.authenticate("chave-secreta").unwrap(),
+            Identity {
+                subject: "ci".to_string(),
+                tenant: "ci".to_string(),
+                method: AuthMethod::ApiKey
+            }
+        );
+        assert!(auth.authenticate("chave-errada").is_err());
+
+        let hs256 = EncodingKey::from_secret(b"segredo");
+        let rs256 = EncodingKey::from_rsa_pem(RS256_PRIVATE_KEY).unwrap();
+        for (alg, key) in [(Algorithm::HS256, &hs256), (Algorithm::RS256, &rs256)] {
+            let identity = auth.authenticate(&token(alg, key, 60, "emissor")).unwrap();
+            assert_eq!(identity.subject, "usuario-1");
+            assert_eq!(identity.tenant, "usuario-1");
+            assert_eq!(identity.method, AuthMethod::Jwt);
+        }
+        let identity = auth
+            .authenticate(&claims_token(
+                Algorithm::HS256,
+                &hs256,
+                60,
+                "emissor",
+                Some("acme"),
+            ))
+            .unwrap();
+        assert_eq!(identity.tenant, "acme");
+
+        // Nem o `tenant` nem o `sub` podem cair no tenant padrão
+        assert!(auth
+            .authenticate(&claims_token(
+                Algorithm::HS256,
+                &hs256,
+                60,
+                "emissor",
+                Some(DEFAULT_TENANT),
+            ))
+            .is_err());
+        let claims = TestClaims {
+            sub: DEFAULT_TENANT,
+            exp: chrono::Utc::now().timestamp() + 60,
+            iss: "emissor",
+            tenant: None,
+        };
+        let reserved = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &hs256);
+        assert!(auth.authenticate(&reserved.unwrap()).is_err());
+
+        // Expirado, emissor errado, segredo errado e algoritmo trocado
+        assert!(auth
+            .authenticate(&token(Algorithm::HS256, &hs256, -120, "emissor"))
+            .is_err());
+        assert!(auth
+            .authenticate(&token(Algorithm::HS256, &hs256, 60, "outro"))
+            .is_err());
+        let wrong = EncodingKey::from_secret(b"outro-segredo");
+        assert!(auth
+            .authenticate(&token(Algorithm::HS256, &wrong, 60, "emissor"))
+            .is_err());
+        assert!(auth
+            .authenticate(&token(Algorithm::HS384, &hs256, 60, "emissor"))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_middleware_rejects_missing_or_invalid_credentials() {
+        let app = Router::new()
+            .route("/api/tasks", get(|| async { "ok" }))
+            .route("/ws", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(
+                Arc::new(authenticator()),
+                require_auth,
+            ))
+            .route("/api/health", get(|| async { "ok" }));
+        let status = |uri: &str, header: Option<(&str, &str)>| {
+            let mut request = Request::builder().uri(uri);
+            if let Some((name, value)) = header {
+                request = request.header(name, value);
+            }
+            let mut app = app.clone();
+            async move {
+                app.call(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(status("/api/health", None).await, StatusCode::OK);
+        assert_eq!(status("/api/tasks", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status("/api/tasks", Some(("authorization", "Bearer errada"))).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(
+                "/api/tasks",
+                Some(("authorization", "Bearer chave-secreta"))
+            )
+            .await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status("/api/tasks", Some(("x-api-key", "chave-secreta"))).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status("/api/tasks?access_token=chave-secreta", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status("/ws?access_token=chave-secreta", None).await,
+            StatusCode::OK
+        );
+    }
+}
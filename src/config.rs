@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::models::DEFAULT_TENANT;
+
+pub const DEFAULT_MAX_CONCURRENCY: usize = 16;
+pub const DEFAULT_CANCEL_GRACE_MS: u64 = 5_000;
+pub const DEFAULT_OUTPUT_MAX_BYTES: usize = 64 * 1024;
+pub const DEFAULT_IDEMPOTENCY_WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+pub const DEFAULT_TASK_LOG_MAX_LINES: usize = 1000;
+pub const DEFAULT_RETENTION_INTERVAL_MS: u64 = 60_000;
+pub const DEFAULT_STORE_PATH: &str = "data/tasks.log";
+pub const DEFAULT_DEAD_LETTER_PATH: &str = "data/dead_letters.log";
+pub const DEFAULT_SCHEDULE_PATH: &str = "data/schedules.json";
+pub const DEFAULT_SERVICE_NAME: &str = "task-processor";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub port: u16,
+    pub processor: ProcessorConfig,
+    pub store: StoreConfig,
+    // Diretório com `<tipo>.json`: JSON Schema do payload de cada tipo de tarefa
+    pub schema_dir: Option<PathBuf>,
+    pub retention: RetentionConfig,
+    pub telemetry: TelemetryConfig,
+    pub auth: AuthConfig,
+    // Origens aceitas pelo CORS; vazio aceita qualquer origem
+    pub cors_origins: Vec<String>,
+    // `/metrics` sem credencial; por padrão exige a mesma autenticação da API
+    pub metrics_public: bool,
+}
+
+// Sem chaves de API nem chaves JWT configuradas, a API fica aberta
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    // Pares (identidade, chave); a identidade também é o tenant da chave
+    pub api_keys: Vec<(String, String)>,
+    pub jwt_hs256_secret: Option<String>,
+    // Arquivo PEM com a chave pública RSA que assina os tokens RS256
+    pub jwt_rs256_public_key: Option<PathBuf>,
+    // Quando definidos, `iss` e `aud` do token precisam coincidir
+    pub jwt_issuer: Option<String>,
+    pub jwt_audience: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    // Coletor OTLP/HTTP (ex.: http://localhost:4318); sem ele, os spans não são exportados
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+    pub log_format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    // Um objeto JSON por linha, para pipelines de log
+    Json,
+}
+
+// Limites de permanência de tarefas finalizadas; sem limite, a tarefa fica para sempre
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_age_ms: Option<u64>,
+    pub max_count: Option<usize>,
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_ms.is_some() || self.max_count.is_some()
+    }
+
+    // Zero é aceito: a tarefa finalizada é removida na próxima passagem
+    fn from_env(prefix: &str) -> Result<Self, String> {
+        Ok(Self {
+            max_age_ms: env_opt(&format!("{}_MAX_AGE_MS", prefix))?,
+            max_count: env_opt(&format!("{}_MAX_COUNT", prefix))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionConfig {
+    pub completed: RetentionPolicy,
+    // Tarefas falhas ficam na DLQ; a política vale para ela
+    pub failed: RetentionPolicy,
+    pub cancelled: RetentionPolicy,
+    // Intervalo entre as passagens do coletor
+    pub interval_ms: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            completed: RetentionPolicy::default(),
+            failed: RetentionPolicy::default(),
+            cancelled: RetentionPolicy::default(),
+            interval_ms: DEFAULT_RETENTION_INTERVAL_MS,
+        }
+    }
+}
+
+impl RetentionConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.completed.is_enabled() || self.failed.is_enabled() || self.cancelled.is_enabled()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreBackend {
+    Memory,
+    File,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoreConfig {
+    pub backend: StoreBackend,
+    pub path: PathBuf,
+    pub dead_letter_path: PathBuf,
+    pub schedule_path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessorConfig {
+    // Limite global de tarefas executando ao mesmo tempo
+    pub max_concurrency: usize,
+    // Limites opcionais por tipo de tarefa (`kind`)
+    pub kind_concurrency: HashMap<String, usize>,
+    // Intervalo de espera que promove uma tarefa um nível de prioridade (evita starvation)
+    pub queue_aging_ms: Option<u64>,
+    // Tempo que um handler cancelado tem para encerrar antes de ser abortado
+    pub cancel_grace_ms: u64,
+    // Tempo máximo de execução para tarefas criadas sem `timeout_ms`
+    pub default_timeout_ms: Option<u64>,
+    // Tamanho máximo (JSON serializado) de um resultado guardado na própria tarefa
+    pub output_max_bytes: usize,
+    // Diretório para resultados acima do limite; sem ele, a tarefa falha
+    pub output_spill_dir: Option<PathBuf>,
+    // Por quanto tempo uma `Idempotency-Key` devolve a tarefa original
+    pub idempotency_window_ms: u64,
+    // Linhas mantidas no log de cada tarefa; as mais antigas são descartadas
+    pub task_log_max_lines: usize,
+    pub tenant_quotas: TenantQuotas,
+}
+
+// Cotas por tenant; sem limite padrão nem limite específico, o tenant não tem cota
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TenantQuotas {
+    // Tarefas ainda não iniciadas (bloqueadas, agendadas, pendentes ou aguardando nova tentativa)
+    pub max_pending: Option<usize>,
+    // Tarefas do tenant executando ao mesmo tempo
+    pub max_concurrency: Option<usize>,
+    // Limites específicos de alguns tenants, no lugar dos padrões acima
+    pub pending_limits: HashMap<String, usize>,
+    pub concurrency_limits: HashMap<String, usize>,
+}
+
+impl TenantQuotas {
+    pub fn pending_limit(&self, tenant: &str) -> Option<usize> {
+        self.pending_limits
+            .get(tenant)
+            .copied()
+            .or(self.max_pending)
+    }
+
+    pub fn concurrency_limit(&self, tenant: &str) -> Option<usize> {
+        self.concurrency_limits
+            .get(tenant)
+            .copied()
+            .or(self.max_concurrency)
+    }
+}
+
+impl Default for ProcessorConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            kind_concurrency: HashMap::new(),
+            queue_aging_ms: None,
+            cancel_grace_ms: DEFAULT_CANCEL_GRACE_MS,
+            default_timeout_ms: None,
+            output_max_bytes: DEFAULT_OUTPUT_MAX_BYTES,
+            output_spill_dir: None,
+            idempotency_window_ms: DEFAULT_IDEMPOTENCY_WINDOW_MS,
+            task_log_max_lines: DEFAULT_TASK_LOG_MAX_LINES,
+            tenant_quotas: TenantQuotas::default(),
+        }
+    }
+}
+
+impl Config {
+    // Um valor que não converte impede a inicialização em vez de cair no padrão
+    pub fn from_env() -> Result<Self, String> {
+        // Usar porta do Railway ou padrão 3000
+        let port = env_or("PORT", 3000)?;
+
+        let processor = ProcessorConfig {
+            max_concurrency: env_positive("WORKER_MAX_CONCURRENCY")?
+                .unwrap_or(DEFAULT_MAX_CONCURRENCY),
+            kind_concurrency: env_limits("WORKER_KIND_CONCURRENCY")?,
+            queue_aging_ms: env_positive("QUEUE_AGING_MS")?,
+            cancel_grace_ms: env_or("CANCEL_GRACE_MS", DEFAULT_CANCEL_GRACE_MS)?,
+            default_timeout_ms: env_positive("TASK_DEFAULT_TIMEOUT_MS")?,
+            output_max_bytes: env_positive("OUTPUT_MAX_BYTES")?.unwrap_or(DEFAULT_OUTPUT_MAX_BYTES),
+            output_spill_dir: env_opt("OUTPUT_SPILL_DIR")?,
+            idempotency_window_ms: env_or("IDEMPOTENCY_WINDOW_MS", DEFAULT_IDEMPOTENCY_WINDOW_MS)?,
+            task_log_max_lines: env_positive("TASK_LOG_MAX_LINES")?
+                .unwrap_or(DEFAULT_TASK_LOG_MAX_LINES),
+            // Ex.: TENANT_MAX_PENDING=1000, TENANT_CONCURRENCY_LIMITS=ci=8,painel=2
+            tenant_quotas: TenantQuotas {
+                max_pending: env_positive("TENANT_MAX_PENDING")?,
+                max_concurrency: env_positive("TENANT_MAX_CONCURRENCY")?,
+                pending_limits: env_limits("TENANT_PENDING_LIMITS")?,
+                concurrency_limits: env_limits("TENANT_CONCURRENCY_LIMITS")?,
+            },
+        };
+
+        // TASK_STORE=file mantém as tarefas em disco entre reinícios
+        let store = StoreConfig {
+            backend: match env_opt::<String>("TASK_STORE")?.as_deref() {
+                None | Some("memory") => StoreBackend::Memory,
+                Some("file") => StoreBackend::File,
+                // Um erro de digitação não pode subir um servidor sem persistência
+                Some(other) => {
+                    return Err(format!(
+                        "TASK_STORE: valor inválido {:?} (esperado memory ou file)",
+                        other
+                    ))
+                }
+            },
+            path: std::env::var("TASK_STORE_PATH")
+                .unwrap_or_else(|_| DEFAULT_STORE_PATH.to_string())
+                .into(),
+            dead_letter_path: std::env::var("DEAD_LETTER_STORE_PATH")
+                .unwrap_or_else(|_| DEFAULT_DEAD_LETTER_PATH.to_string())
+                .into(),
+            schedule_path: std::env::var("SCHEDULE_STORE_PATH")
+                .unwrap_or_else(|_| DEFAULT_SCHEDULE_PATH.to_string())
+                .into(),
+        };
+
+        Ok(Self {
+            port,
+            processor,
+            store,
+            schema_dir: env_opt("TASK_SCHEMA_DIR")?,
+            // Ex.: RETAIN_COMPLETED_MAX_AGE_MS=86400000, RETAIN_CANCELLED_MAX_COUNT=1000
+            retention: RetentionConfig {
+                completed: RetentionPolicy::from_env("RETAIN_COMPLETED")?,
+                failed: RetentionPolicy::from_env("RETAIN_FAILED")?,
+                cancelled: RetentionPolicy::from_env("RETAIN_CANCELLED")?,
+                interval_ms: env_positive("RETENTION_INTERVAL_MS")?
+                    .unwrap_or(DEFAULT_RETENTION_INTERVAL_MS),
+            },
+            telemetry: TelemetryConfig {
+                otlp_endpoint: env_opt("OTEL_EXPORTER_OTLP_ENDPOINT")?,
+                service_name: env_opt("OTEL_SERVICE_NAME")?
+                    .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string()),
+                // LOG_FORMAT=json troca o formato legível por JSON
+                log_format: match env_opt::<String>("LOG_FORMAT")?.as_deref() {
+                    None | Some("text") => LogFormat::Text,
+                    Some("json") => LogFormat::Json,
+                    Some(other) => {
+                        return Err(format!(
+                            "LOG_FORMAT: valor inválido {:?} (esperado text ou json)",
+                            other
+                        ))
+                    }
+                },
+            },
+            auth: AuthConfig {
+                api_keys: std::env::var("AUTH_API_KEYS")
+                    .map(|value| parse_api_keys(&value))
+                    .unwrap_or(Ok(Vec::new()))?,
+                jwt_hs256_secret: env_opt("AUTH_JWT_HS256_SECRET")?,
+                jwt_rs256_public_key: env_opt("AUTH_JWT_RS256_PUBLIC_KEY_FILE")?,
+                jwt_issuer: env_opt("AUTH_JWT_ISSUER")?,
+                jwt_audience: env_opt("AUTH_JWT_AUDIENCE")?,
+            },
+            cors_origins: std::env::var("CORS_ALLOWED_ORIGINS")
+                .map(|value| parse_list(&value))
+                .unwrap_or_default(),
+            metrics_public: env_or("METRICS_PUBLIC", false)?,
+        })
+    }
+}
+
+// Variável ausente ou vazia: `None`; um valor que não converte é erro
+fn env_opt<T: FromStr>(key: &str) -> Result<Option<T>, String> {
+    match std::env::var(key) {
+        Ok(value) if !value.trim().is_empty() => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{}: valor inválido {:?}", key, value)),
+        _ => Ok(None),
+    }
+}
+
+fn env_or<T: FromStr>(key: &str, default: T) -> Result<T, String> {
+    Ok(env_opt(key)?.unwrap_or(default))
+}
+
+// Limites e intervalos: zero não tem uso e é recusado
+fn env_positive<T: FromStr + PartialEq + From<u8>>(key: &str) -> Result<Option<T>, String> {
+    match env_opt(key)? {
+        Some(value) if value == T::from(0) => Err(format!("{}: deve ser maior que zero", key)),
+        value => Ok(value),
+    }
+}
+
+fn env_limits(key: &str) -> Result<HashMap<String, usize>, String> {
+    match std::env::var(key) {
+        Ok(value) => parse_kind_limits(&value).map_err(|e| format!("{}: {}", key, e)),
+        Err(_) => Ok(HashMap::new()),
+    }
+}
+
+// Formato: "simulate=4,email=2"; também usado nos limites por tenant
+fn parse_kind_limits(value: &str) -> Result<HashMap<String, usize>, String> {
+    parse_list(value)
+        .into_iter()
+        .map(|entry| {
+            let invalid = || format!("entrada inválida {:?} (esperado nome=limite)", entry);
+            let (name, limit) = entry.split_once('=').ok_or_else(invalid)?;
+            let name = name.trim();
+            let limit: usize = limit.trim().parse().map_err(|_| invalid())?;
+            if name.is_empty() {
+                return Err(invalid());
+            }
+            if limit == 0 {
+                return Err(format!("o limite de {} deve ser maior que zero", name));
+            }
+            Ok((name.to_string(), limit))
+        })
+        .collect()
+}
+
+// Formato: "ci=chave1,painel=chave2". O nome é obrigatório: é ele que separa
+// os tenants, e chaves sem nome cairiam todas no mesmo
+fn parse_api_keys(value: &str) -> Result<Vec<(String, String)>, String> {
+    parse_list(value)
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| match entry.split_once('=') {
+            // O tenant padrão é o das tarefas criadas sem autenticação
+            Some((name, _)) if name.trim() == DEFAULT_TENANT => Err(format!(
+                "AUTH_API_KEYS: a entrada {} usa o nome reservado {:?}",
+                i + 1,
+                DEFAULT_TENANT
+            )),
+            Some((name, key)) if !name.trim().is_empty() && !key.trim().is_empty() => {
+                Ok((name.trim().to_string(), key.trim().to_string()))
+            }
+            // A entrada não aparece na mensagem: pode ser a própria chave
+            _ => Err(format!(
+                "AUTH_API_KEYS: a entrada {} não está no formato nome=chave",
+                i + 1
+            )),
+        })
+        .collect()
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|entry| entry.trim().to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kind_limits() {
+        let limits = parse_kind_limits("simulate=4, email = 2,").unwrap();
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits["simulate"], 4);
+        assert_eq!(limits["email"], 2);
+        // Entradas inválidas e limites zero impedem a inicialização
+        for value in ["invalido", "=3", "zero=0", "email=dois", "email=-1"] {
+            assert!(parse_kind_limits(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_parse_api_keys() {
+        let keys = parse_api_keys("ci=abc, painel = xyz,").unwrap();
+        assert_eq!(
+            keys,
+            vec![
+                ("ci".to_string(), "abc".to_string()),
+                ("painel".to_string(), "xyz".to_string()),
+            ]
+        );
+        // Sem nome, todas as chaves cairiam no mesmo tenant
+        assert!(parse_api_keys("ci=abc,solta").is_err());
+        assert!(parse_api_keys("=abc").is_err());
+        assert!(parse_api_keys("vazia=").is_err());
+        assert!(parse_api_keys("ci=abc,default=xyz").is_err());
+    }
+}
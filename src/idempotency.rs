@@ -0,0 +1,203 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+pub const MAX_KEY_LEN: usize = 255;
+
+// Cada tenant tem seu próprio espaço de chaves
+type Key = (String, String);
+
+// Chaves de idempotência vistas dentro da janela, com a tarefa criada por cada uma.
+// As entradas expiram pela data de criação da tarefa.
+#[derive(Debug)]
+pub struct IdempotencyKeys {
+    window: Duration,
+    keys: HashMap<Key, (Uuid, DateTime<Utc>)>,
+    expiry: BTreeSet<(DateTime<Utc>, Key)>,
+    // Chaves cuja tarefa está sendo gravada; o canal fecha quando a gravação termina
+    reserved: HashMap<Key, watch::Sender<()>>,
+}
+
+pub enum Reservation {
+    // Chave já usada dentro da janela: tarefa criada por ela
+    Existing(Uuid),
+    // Outra criação com a mesma chave está gravando; `changed()` retorna quando ela termina
+    InFlight(watch::Receiver<()>),
+    // A chave fica com quem a reservou até `insert` ou `release`
+    Reserved,
+}
+
+impl IdempotencyKeys {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            keys: HashMap::new(),
+            expiry: BTreeSet::new(),
+            reserved: HashMap::new(),
+        }
+    }
+
+    pub fn reserve(&mut self, tenant: &str, key: &str, now: DateTime<Utc>) -> Reservation {
+        self.prune(now);
+        let key = (tenant.to_string(), key.to_string());
+        if let Some(sender) = self.reserved.get(&key) {
+            return Reservation::InFlight(sender.subscribe());
+        }
+        if let Some((id, _)) = self.keys.get(&key) {
+            return Reservation::Existing(*id);
+        }
+        self.reserved.insert(key, watch::channel(()).0);
+        Reservation::Reserved
+    }
+
+    // Desfaz a reserva sem registrar tarefa; quem esperava tenta de novo
+    pub fn release(&mut self, tenant: &str, key: &str) {
+        self.reserved.remove(&(tenant.to_string(), key.to_string()));
+    }
+
+    // Esquece a chave de uma tarefa que não existe mais
+    pub fn forget(&mut self, tenant: &str, key: &str) {
+        let key = (tenant.to_string(), key.to_string());
+        if let Some((_, created_at)) = self.keys.remove(&key) {
+            self.expiry.remove(&(created_at, key));
+        }
+    }
+
+    pub fn insert(&mut self, tenant: String, key: String, id: Uuid, created_at: DateTime<Utc>) {
+        let key = (tenant, key);
+        self.reserved.remove(&key);
+        if let Some((_, previous)) = self.keys.insert(key.clone(), (id, created_at)) {
+            self.expiry.remove(&(previous, key.clone()));
+        }
+        self.expiry.insert((created_at, key));
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        while let Some((at, key)) = self.expiry.first() {
+            if *at + self.window > now {
+                break;
+            }
+            self.keys.remove(key);
+            self.expiry.pop_first();
+        }
+    }
+}
+
+// Reserva de uma chave enquanto a tarefa é gravada, sem segurar o lock das
+// chaves durante a gravação. Sem `commit`, é desfeita ao sair do escopo.
+pub struct KeyReservation<'a> {
+    keys: &'a Mutex<IdempotencyKeys>,
+    key: Option<Key>,
+}
+
+impl<'a> KeyReservation<'a> {
+    // Só deve ser criada depois de `reserve` devolver `Reserved`
+    pub fn new(keys: &'a Mutex<IdempotencyKeys>, tenant: &str, key: &str) -> Self {
+        Self {
+            keys,
+            key: Some((tenant.to_string(), key.to_string())),
+        }
+    }
+
+    pub fn commit(mut self, id: Uuid, created_at: DateTime<Utc>) {
+        if let Some((tenant, key)) = self.key.take() {
+            self.keys
+                .lock()
+                .unwrap()
+                .insert(tenant, key, id, created_at);
+        }
+    }
+}
+
+impl Drop for KeyReservation<'_> {
+    fn drop(&mut self) {
+        if let Some((tenant, key)) = self.key.take() {
+            self.keys.lock().unwrap().release(&tenant, &key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(
+        keys: &mut IdempotencyKeys,
+        tenant: &str,
+        key: &str,
+        now: DateTime<Utc>,
+    ) -> Option<Uuid> {
+        match keys.reserve(tenant, key, now) {
+            Reservation::Existing(id) => Some(id),
+            Reservation::Reserved => {
+                keys.release(tenant, key);
+                None
+            }
+            Reservation::InFlight(_) => panic!("nenhuma chave deveria estar reservada"),
+        }
+    }
+
+    #[test]
+    fn test_keys_expire_after_window() {
+        let now = Utc::now();
+        let (old, recent) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut keys = IdempotencyKeys::new(Duration::minutes(10));
+        // Recuperação após reinício insere fora de ordem
+        keys.insert(
+            "t".to_string(),
+            "b".to_string(),
+            recent,
+            now - Duration::minutes(1),
+        );
+        keys.insert(
+            "t".to_string(),
+            "a".to_string(),
+            old,
+            now - Duration::minutes(20),
+        );
+
+        assert_eq!(lookup(&mut keys, "t", "a", now), None);
+        assert_eq!(lookup(&mut keys, "t", "b", now), Some(recent));
+        assert_eq!(lookup(&mut keys, "outro", "b", now), None);
+        assert_eq!(
+            lookup(&mut keys, "t", "b", now + Duration::minutes(9)),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reservation_blocks_same_key_until_released() {
+        let now = Utc::now();
+        let keys = Mutex::new(IdempotencyKeys::new(Duration::minutes(10)));
+        assert!(matches!(
+            keys.lock().unwrap().reserve("t", "a", now),
+            Reservation::Reserved
+        ));
+        let reservation = KeyReservation::new(&keys, "t", "a");
+
+        let Reservation::InFlight(mut waiting) = keys.lock().unwrap().reserve("t", "a", now) else {
+            panic!("a chave deveria estar reservada");
+        };
+        // Outro tenant tem o próprio espaço de chaves
+        assert!(matches!(
+            keys.lock().unwrap().reserve("outro", "a", now),
+            Reservation::Reserved
+        ));
+
+        drop(reservation);
+        assert!(waiting.changed().await.is_err());
+        assert!(matches!(
+            keys.lock().unwrap().reserve("t", "a", now),
+            Reservation::Reserved
+        ));
+
+        let id = Uuid::new_v4();
+        KeyReservation::new(&keys, "t", "a").commit(id, now);
+        assert!(matches!(
+            keys.lock().unwrap().reserve("t", "a", now),
+            Reservation::Existing(found) if found == id
+        ));
+    }
+}
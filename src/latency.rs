@@ -0,0 +1,312 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::str::FromStr;
+
+use crate::models::TaskPriority;
+
+// Cada bucket cobre valores até 5% maiores que o anterior: percentis com erro
+// relativo de no máximo 5%, sem guardar cada amostra
+const GROWTH: f64 = 1.05;
+// Granularidade das janelas deslizantes; a janela de 1 hora guarda 360 fatias
+const SLOT_SECS: i64 = 10;
+const MAX_SLOTS: i64 = 3600 / SLOT_SECS;
+
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    buckets: BTreeMap<u32, u64>,
+    count: u64,
+    sum: u64,
+    max: u64,
+}
+
+impl Histogram {
+    pub fn record(&mut self, ms: u64) {
+        *self.buckets.entry(bucket(ms)).or_default() += 1;
+        self.count += 1;
+        self.sum += ms;
+        self.max = self.max.max(ms);
+    }
+
+    pub fn merge(&mut self, other: &Histogram) {
+        for (bucket, count) in &other.buckets {
+            *self.buckets.entry(*bucket).or_default() += count;
+        }
+        self.count += other.count;
+        self.sum += other.sum;
+        self.max = self.max.max(other.max);
+    }
+
+    // Limite superior do bucket que contém o quantil `q`, nunca acima do máximo visto
+    pub fn quantile(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((q * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (bucket, count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                return upper_bound(*bucket).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    // Amostras em buckets inteiramente até `ms`; as de um bucket que cruza o
+    // limite contam só nos limites seguintes
+    pub fn count_at_most(&self, ms: u64) -> u64 {
+        self.buckets
+            .iter()
+            .take_while(|(bucket, _)| upper_bound(**bucket) <= ms)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            count: self.count,
+            p50: self.quantile(0.5),
+            p90: self.quantile(0.9),
+            p99: self.quantile(0.99),
+            max: self.max,
+        }
+    }
+}
+
+fn bucket(ms: u64) -> u32 {
+    if ms == 0 {
+        return 0;
+    }
+    ((ms as f64).ln() / GROWTH.ln()).ceil() as u32 + 1
+}
+
+fn upper_bound(bucket: u32) -> u64 {
+    if bucket == 0 {
+        return 0;
+    }
+    GROWTH.powi(bucket as i32 - 1).round() as u64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LatencyWindow {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+impl LatencyWindow {
+    fn slots(self) -> i64 {
+        match self {
+            LatencyWindow::OneMinute => 60 / SLOT_SECS,
+            LatencyWindow::FiveMinutes => 300 / SLOT_SECS,
+            LatencyWindow::OneHour => MAX_SLOTS,
+        }
+    }
+}
+
+impl FromStr for LatencyWindow {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "1m" => Ok(LatencyWindow::OneMinute),
+            "5m" => Ok(LatencyWindow::FiveMinutes),
+            "1h" => Ok(LatencyWindow::OneHour),
+            other => Err(format!(
+                "Janela desconhecida: '{}' (use 1m, 5m ou 1h)",
+                other
+            )),
+        }
+    }
+}
+
+// Histograma desde o início do processo mais fatias de 10 s da última hora,
+// somadas sob demanda para responder pelas janelas deslizantes
+#[derive(Debug, Clone, Default)]
+pub struct WindowedHistogram {
+    total: Histogram,
+    slots: VecDeque<(i64, Histogram)>,
+}
+
+impl WindowedHistogram {
+    pub fn record(&mut self, ms: u64, at: DateTime<Utc>) {
+        self.total.record(ms);
+        let slot = at.timestamp().div_euclid(SLOT_SECS);
+        match self.slots.iter_mut().rev().find(|(s, _)| *s == slot) {
+            Some((_, histogram)) => histogram.record(ms),
+            None => {
+                let mut histogram = Histogram::default();
+                histogram.record(ms);
+                let position = self.slots.partition_point(|(s, _)| *s < slot);
+                self.slots.insert(position, (slot, histogram));
+            }
+        }
+        while let Some((oldest, _)) = self.slots.front() {
+            if *oldest > self.newest_slot() - MAX_SLOTS {
+                break;
+            }
+            self.slots.pop_front();
+        }
+    }
+
+    fn newest_slot(&self) -> i64 {
+        self.slots.back().map_or(i64::MIN, |(slot, _)| *slot)
+    }
+
+    pub fn histogram(&self, window: Option<LatencyWindow>, now: DateTime<Utc>) -> Histogram {
+        let Some(window) = window else {
+            return self.total.clone();
+        };
+        let current = now.timestamp().div_euclid(SLOT_SECS);
+        let mut histogram = Histogram::default();
+        for (_, slot) in self
+            .slots
+            .iter()
+            .filter(|(slot, _)| *slot > current - window.slots() && *slot <= current)
+        {
+            histogram.merge(slot);
+        }
+        histogram
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyBreakdown {
+    pub all: LatencySummary,
+    pub by_priority: HashMap<TaskPriority, LatencySummary>,
+    pub by_kind: BTreeMap<String, LatencySummary>,
+}
+
+// Latências em milissegundos; `window` ausente significa desde o início do processo
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub window: Option<LatencyWindow>,
+    // Da criação (ou de `run_at`) até o início da primeira execução
+    pub queue_wait_ms: LatencyBreakdown,
+    // Do início ao fim de cada execução, com sucesso ou falha
+    pub run_time_ms: LatencyBreakdown,
+}
+
+pub type LabelledHistogram = (TaskPriority, String, Histogram);
+
+// Histogramas por prioridade e tipo juntos, desde o início do processo (usados em `/metrics`)
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistograms {
+    pub queue_wait: Vec<LabelledHistogram>,
+    pub run_time: Vec<LabelledHistogram>,
+}
+
+#[derive(Debug, Default)]
+pub struct LatencySeries {
+    all: WindowedHistogram,
+    by_priority: HashMap<TaskPriority, WindowedHistogram>,
+    by_kind: HashMap<String, WindowedHistogram>,
+    by_label: HashMap<(TaskPriority, String), Histogram>,
+}
+
+impl LatencySeries {
+    pub fn record(&mut self, priority: &TaskPriority, kind: &str, ms: u64, at: DateTime<Utc>) {
+        self.all.record(ms, at);
+        self.by_priority
+            .entry(priority.clone())
+            .or_default()
+            .record(ms, at);
+        self.by_kind
+            .entry(kind.to_string())
+            .or_default()
+            .record(ms, at);
+        self.by_label
+            .entry((priority.clone(), kind.to_string()))
+            .or_default()
+            .record(ms);
+    }
+
+    pub fn by_label(&self) -> Vec<LabelledHistogram> {
+        self.by_label
+            .iter()
+            .map(|((priority, kind), h)| (priority.clone(), kind.clone(), h.clone()))
+            .collect()
+    }
+
+    pub fn breakdown(&self, window: Option<LatencyWindow>, now: DateTime<Utc>) -> LatencyBreakdown {
+        LatencyBreakdown {
+            all: self.all.histogram(window, now).summary(),
+            by_priority: self
+                .by_priority
+                .iter()
+                .map(|(priority, h)| (priority.clone(), h.histogram(window, now).summary()))
+                .collect(),
+            by_kind: self
+                .by_kind
+                .iter()
+                .map(|(kind, h)| (kind.clone(), h.histogram(window, now).summary()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_percentiles_stay_within_bucket_error() {
+        let mut histogram = Histogram::default();
+        for ms in 1..=1000 {
+            histogram.record(ms);
+        }
+
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 1000);
+        assert_eq!(summary.max, 1000);
+        for (value, expected) in [(summary.p50, 500), (summary.p90, 900), (summary.p99, 990)] {
+            assert!(value >= expected && value as f64 <= expected as f64 * GROWTH + 1.0);
+        }
+        assert_eq!(Histogram::default().summary(), LatencySummary::default());
+    }
+
+    #[test]
+    fn test_windows_only_include_recent_samples() {
+        let now = Utc::now();
+        let mut histogram = WindowedHistogram::default();
+        histogram.record(5000, now - Duration::minutes(90));
+        histogram.record(3000, now - Duration::minutes(30));
+        histogram.record(2000, now - Duration::minutes(3));
+        histogram.record(1000, now);
+
+        let count = |window| histogram.histogram(window, now).summary().count;
+        assert_eq!(count(None), 4);
+        assert_eq!(count(Some(LatencyWindow::OneHour)), 3);
+        assert_eq!(count(Some(LatencyWindow::FiveMinutes)), 2);
+        assert_eq!(count(Some(LatencyWindow::OneMinute)), 1);
+        assert_eq!(
+            histogram
+                .histogram(Some(LatencyWindow::FiveMinutes), now)
+                .summary()
+                .max,
+            2000
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            other => Err(format!("Nível de log desconhecido: '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogLine {
+    // Crescente entre todas as tarefas; serve de cursor na leitura e no tail
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub attempt: u32,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+// Últimas linhas escritas pelos handlers de cada tarefa. Ficam só em memória:
+// não sobrevivem a um reinício.
+#[derive(Debug)]
+pub struct TaskLogs {
+    max_lines: usize,
+    lines: Mutex<HashMap<Uuid, VecDeque<LogLine>>>,
+    seq: AtomicU64,
+    sender: broadcast::Sender<(Uuid, LogLine)>,
+}
+
+impl TaskLogs {
+    pub fn new(max_lines: usize) -> Self {
+        let (sender, _) = broadcast::channel(1000);
+        Self {
+            max_lines: max_lines.max(1),
+            lines: Mutex::new(HashMap::new()),
+            seq: AtomicU64::new(0),
+            sender,
+        }
+    }
+
+    pub fn append(&self, id: Uuid, attempt: u32, level: LogLevel, message: String) -> LogLine {
+        let line = LogLine {
+            seq: self.seq.fetch_add(1, Ordering::Relaxed) + 1,
+            at: Utc::now(),
+            attempt,
+            level,
+            message,
+        };
+        {
+            let mut lines = self.lines.lock().unwrap();
+            let buffer = lines.entry(id).or_default();
+            if buffer.len() == self.max_lines {
+                buffer.pop_front();
+            }
+            buffer.push_back(line.clone());
+        }
+        let _ = self.sender.send((id, line.clone()));
+        line
+    }
+
+    // Linhas com `seq` maior que `after`, da mais antiga para a mais recente
+    pub fn read(&self, id: Uuid, after: Option<u64>, limit: usize) -> Vec<LogLine> {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .get(&id)
+            .map(|buffer| {
+                buffer
+                    .iter()
+                    .filter(|line| after.is_none_or(|seq| line.seq > seq))
+                    .take(limit)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn remove(&self, id: Uuid) {
+        self.lines.lock().unwrap().remove(&id);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<(Uuid, LogLine)> {
+        self.sender.subscribe()
+    }
+}
+
+// Escrita no log de uma execução, entregue ao handler pelo `TaskContext`
+#[derive(Debug, Clone)]
+pub struct TaskLogger {
+    id: Uuid,
+    attempt: u32,
+    logs: Arc<TaskLogs>,
+}
+
+impl TaskLogger {
+    pub fn new(id: Uuid, attempt: u32, logs: Arc<TaskLogs>) -> Self {
+        Self { id, attempt, logs }
+    }
+
+    pub fn log(&self, level: LogLevel, message: String) {
+        self.logs.append(self.id, self.attempt, level, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_keeps_latest_lines_and_reads_after_cursor() {
+        let logs = TaskLogs::new(3);
+        let (id, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut rx = logs.subscribe();
+        for i in 0..5 {
+            logs.append(id, 1, LogLevel::Info, format!("linha {}", i));
+        }
+        logs.append(other, 1, LogLevel::Error, "outra".to_string());
+
+        let lines = logs.read(id, None, 100);
+        let messages: Vec<_> = lines.iter().map(|line| line.message.as_str()).collect();
+        assert_eq!(messages, vec!["linha 2", "linha 3", "linha 4"]);
+
+        let after = logs.read(id, Some(lines[0].seq), 1);
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].message, "linha 3");
+
+        assert_eq!(rx.try_recv().unwrap().1.message, "linha 0");
+        logs.remove(id);
+        assert!(logs.read(id, None, 100).is_empty());
+        assert_eq!(logs.read(other, None, 100).len(), 1);
+    }
+}
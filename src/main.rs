@@ -1,74 +1,193 @@
 mod api;
+mod auth;
+mod config;
+mod idempotency;
+mod latency;
+mod logs;
+mod metrics;
 mod models;
+mod output;
 mod processor;
+mod queue;
+mod registry;
+mod scheduler;
+mod store;
+mod telemetry;
+mod timer;
 mod websocket;
+mod worker;
 
 use anyhow::Result;
 use axum::{
     extract::ws::WebSocketUpgrade,
-    extract::State,
+    extract::{DefaultBodyLimit, State},
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware,
     response::Response,
     routing::{get, post},
     Router,
 };
 use std::sync::Arc;
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing::info;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing::{info, warn};
 
-use crate::api::handlers;
+use crate::api::{bulk, dead_letters, handlers, schedules, workflows};
+use crate::auth::Authenticator;
+use crate::config::{Config, StoreBackend};
+use crate::metrics::Metrics;
 use crate::processor::TaskProcessor;
+use crate::registry::HandlerRegistry;
+use crate::scheduler::Scheduler;
 
 #[derive(Clone)]
 struct AppState {
     processor: Arc<TaskProcessor>,
+    scheduler: Arc<Scheduler>,
+    metrics: Arc<Metrics>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Inicializar logging
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "task_processor=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let config = Config::from_env().map_err(anyhow::Error::msg)?;
+
+    // Inicializar logging e tracing
+    let tracer_provider = telemetry::init(&config.telemetry)?;
 
     info!("🚀 Iniciando Task Processor Server...");
+    if let Some(endpoint) = &config.telemetry.otlp_endpoint {
+        info!("🔭 Exportando spans via OTLP para {}", endpoint);
+    }
+
+    // Abrir armazenamento de tarefas
+    let store = store::open(&config.store)?;
+    let dead_letters = store::open_dead_letters(&config.store)?;
+    info!("💾 Armazenamento de tarefas: {:?}", config.store.backend);
+
+    let mut registry = HandlerRegistry::with_builtins();
+    if let Some(dir) = &config.schema_dir {
+        let kinds = registry.load_schemas(dir).map_err(anyhow::Error::msg)?;
+        info!("📐 Schemas de payload carregados: {:?}", kinds);
+    }
 
     // Criar processador de tarefas
-    let processor = Arc::new(TaskProcessor::new());
-    let state = AppState { processor };
+    let processor = Arc::new(TaskProcessor::with_store(
+        config.processor.clone(),
+        registry,
+        store,
+        dead_letters,
+    ));
+    let requeued = processor.recover().map_err(anyhow::Error::msg)?;
+    if requeued > 0 {
+        info!("♻️ {} tarefas recuperadas de volta à fila", requeued);
+    }
+    info!(
+        "👷 Pool de workers: {} vagas (limites por tipo: {:?})",
+        config.processor.max_concurrency, config.processor.kind_concurrency
+    );
+    info!(
+        "🧩 Tipos de tarefa registrados: {:?}",
+        processor.registry().kinds()
+    );
+
+    // Agendamentos recorrentes são persistidos junto com as tarefas
+    let schedule_path = (config.store.backend == StoreBackend::File)
+        .then_some(config.store.schedule_path.as_path());
+    let scheduler = Arc::new(Scheduler::open(processor.clone(), schedule_path)?);
+    let state = AppState {
+        processor,
+        scheduler,
+        metrics: Arc::new(Metrics::new()),
+    };
 
     // Iniciar processamento em background
     let processor_clone = state.processor.clone();
     tokio::spawn(async move {
         processor_clone.start_processing().await;
     });
+    tokio::spawn(state.scheduler.clone().run());
+    if config.retention.is_enabled() {
+        info!("🧹 Retenção de tarefas finalizadas: {:?}", config.retention);
+        tokio::spawn(state.processor.clone().run_retention(config.retention.clone()));
+    }
 
-    // Criar router
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/api/health", get(handlers::health_check))
+    let authenticator = Arc::new(Authenticator::from_config(&config.auth)?);
+    if authenticator.is_enabled() {
+        info!("🔐 Autenticação exigida em /api e /ws");
+    } else {
+        warn!("🔓 Nenhuma chave de API ou JWT configurada: a API está aberta");
+    }
+
+    // Criar router; as rotas antes do `route_layer` de autenticação exigem credencial
+    let mut protected = Router::new()
         .route("/api/tasks", post(handlers::create_task))
         .route("/api/tasks", get(handlers::list_tasks))
-        .route("/api/tasks/:id", get(handlers::get_task))
+        .route(
+            "/api/tasks/batch",
+            post(bulk::create_batch).layer(DefaultBodyLimit::max(bulk::MAX_BATCH_BYTES)),
+        )
+        .route("/api/tasks/bulk/cancel", post(bulk::cancel_many))
+        .route("/api/tasks/bulk/requeue", post(bulk::requeue_many))
+        .route("/api/tasks/bulk/delete", post(bulk::delete_many))
+        .route(
+            "/api/tasks/:id",
+            get(handlers::get_task).delete(handlers::delete_task),
+        )
         .route("/api/tasks/:id/cancel", post(handlers::cancel_task))
+        .route("/api/tasks/:id/result", get(handlers::get_task_result))
+        .route("/api/tasks/:id/logs", get(handlers::get_task_logs))
         .route("/api/stats", get(handlers::get_stats))
-        .route("/ws", get(handle_websocket_upgrade))
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+        .route("/api/dead-letters", get(dead_letters::list_dead_letters))
+        .route(
+            "/api/dead-letters/:id",
+            get(dead_letters::get_dead_letter).delete(dead_letters::purge_dead_letter),
+        )
+        .route(
+            "/api/dead-letters/:id/requeue",
+            post(dead_letters::requeue_dead_letter),
+        )
+        .route("/api/dead-letters/requeue", post(dead_letters::requeue_many))
+        .route("/api/dead-letters/purge", post(dead_letters::purge_many))
+        .route(
+            "/api/schedules",
+            post(schedules::create_schedule).get(schedules::list_schedules),
+        )
+        .route(
+            "/api/schedules/:id",
+            get(schedules::get_schedule)
+                .put(schedules::update_schedule)
+                .delete(schedules::delete_schedule),
+        )
+        .route("/api/schedules/:id/pause", post(schedules::pause_schedule))
+        .route("/api/schedules/:id/resume", post(schedules::resume_schedule))
+        .route("/api/schedules/:id/next", get(schedules::preview_schedule))
+        .route("/api/workflows", post(workflows::create_workflow))
+        .route("/api/workflows/:id", get(workflows::get_workflow))
+        .route("/ws", get(handle_websocket_upgrade));
+    // As métricas são globais, de todos os tenants: só ficam públicas com METRICS_PUBLIC=true
+    let mut public = Router::new();
+    if config.metrics_public {
+        public = public.route("/metrics", get(metrics::metrics));
+    } else {
+        protected = protected.route("/metrics", get(metrics::metrics));
+    }
+    let app = protected
+        .route_layer(middleware::from_fn_with_state(
+            authenticator,
+            auth::require_auth,
+        ))
+        .merge(public)
+        .route("/", get(root))
+        .route("/api/health", get(handlers::health_check))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            metrics::track_http,
+        ))
+        .layer(cors_layer(&config.cors_origins)?)
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::http_span))
         .with_state(state);
 
-    // Usar porta do Railway ou padrão 3000
-    let port = std::env::var("PORT")
-        .unwrap_or_else(|_| "3000".to_string())
-        .parse::<u16>()
-        .unwrap_or(3000);
-    
-    let addr = format!("0.0.0.0:{}", port);
+    let addr = format!("0.0.0.0:{}", config.port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     info!("📡 Servidor rodando em http://{}", addr);
     info!("📊 WebSocket disponível em ws://{}/ws", addr);
@@ -76,6 +195,7 @@ async fn main() -> Result<()> {
 
     axum::serve(listener, app).await?;
 
+    let _ = tracer_provider.shutdown();
     Ok(())
 }
 
@@ -87,25 +207,81 @@ async fn root() -> &'static str {
     - GET  /api/health          - Status do servidor
     - POST /api/tasks           - Criar nova tarefa
     - GET  /api/tasks           - Listar todas as tarefas
+    - POST /api/tasks/batch     - Criar tarefas em lote (array JSON ou NDJSON)
+    - POST /api/tasks/bulk/cancel  - Cancelar tarefas por ids ou filtro
+    - POST /api/tasks/bulk/requeue - Devolver tarefas da DLQ à fila por ids ou filtro
+    - POST /api/tasks/bulk/delete  - Remover tarefas finalizadas por ids ou filtro
     - GET  /api/tasks/:id       - Obter tarefa específica
+    - DELETE /api/tasks/:id     - Remover tarefa finalizada
     - POST /api/tasks/:id/cancel - Cancelar tarefa
+    - GET  /api/tasks/:id/result - Aguardar e obter o resultado (long-poll)
+    - GET  /api/tasks/:id/logs  - Log escrito pelos handlers da tarefa
     - GET  /api/stats           - Estatísticas do sistema
+    - GET  /metrics             - Métricas no formato do Prometheus
+    - GET  /api/dead-letters    - Tarefas que falharam definitivamente
+    - GET  /api/dead-letters/:id          - Inspecionar tarefa na DLQ
+    - POST /api/dead-letters/:id/requeue  - Devolver tarefa à fila
+    - DELETE /api/dead-letters/:id        - Remover tarefa da DLQ
+    - POST /api/dead-letters/requeue      - Devolver várias (ou todas) à fila
+    - POST /api/dead-letters/purge        - Remover várias (ou todas) da DLQ
+    - POST /api/schedules       - Criar agendamento recorrente (cron ou intervalo)
+    - GET  /api/schedules       - Listar agendamentos
+    - GET/PUT/DELETE /api/schedules/:id   - Consultar, alterar ou remover agendamento
+    - POST /api/schedules/:id/pause       - Pausar agendamento
+    - POST /api/schedules/:id/resume      - Retomar agendamento
+    - GET  /api/schedules/:id/next        - Prévia das próximas execuções
+    - POST /api/workflows       - Criar workflow (DAG de tarefas)
+    - GET  /api/workflows/:id   - Status do workflow e de suas tarefas
     - WS   /ws                  - WebSocket para atualizações em tempo real
+
+    Com autenticação configurada, /api/* (exceto /api/health) e /ws exigem
+    "Authorization: Bearer <chave ou JWT>" ou "X-API-Key: <chave>".
     
     Exemplo de criação de tarefa:
     POST /api/tasks
     {
         "name": "Processar dados",
+        "kind": "simulate",
         "duration_ms": 5000,
-        "priority": "high"
+        "priority": "high",
+        "payload": {}
     }
     "#
 }
 
+// Sem origens configuradas, aceita qualquer uma (sem cookies; a credencial vai nos cabeçalhos)
+fn cors_layer(origins: &[String]) -> Result<CorsLayer> {
+    let allow_origin = if origins.is_empty() || origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = origins
+            .iter()
+            .map(|origin| HeaderValue::from_str(origin))
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            HeaderName::from_static("x-api-key"),
+            HeaderName::from_static("idempotency-key"),
+            HeaderName::from_static("traceparent"),
+        ])
+        .expose_headers([
+            HeaderName::from_static("idempotent-replayed"),
+            HeaderName::from_static("x-total-count"),
+            HeaderName::from_static("x-next-cursor"),
+        ]))
+}
+
 async fn handle_websocket_upgrade(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    tenant: auth::Tenant,
 ) -> Response {
-    ws.on_upgrade(|socket| websocket::handle_websocket(socket, State(state)))
+    ws.on_upgrade(|socket| websocket::handle_websocket(socket, State(state), tenant))
 }
 
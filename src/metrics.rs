@@ -0,0 +1,397 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::latency::{Histogram, LabelledHistogram};
+use crate::processor::TaskProcessor;
+use crate::AppState;
+
+// Limites dos buckets exportados, em segundos
+const TASK_BUCKETS: [f64; 13] = [
+    0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 600.0, 1800.0, 3600.0,
+];
+const HTTP_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+// Métricas do servidor (HTTP e WebSocket). As de tarefas são lidas do
+// processador no momento da coleta.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    ws_connections: AtomicU64,
+    ws_lagged: AtomicU64,
+    http_requests: Mutex<HashMap<(String, String, u16), u64>>,
+    http_durations: Mutex<HashMap<(String, String), Histogram>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ws_connected(&self) {
+        self.ws_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn ws_disconnected(&self) {
+        self.ws_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // Atualizações descartadas porque o cliente WebSocket ficou para trás
+    pub fn ws_lagged(&self, skipped: u64) {
+        self.ws_lagged.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    pub fn record_http(&self, method: &str, route: &str, status: u16, duration_ms: u64) {
+        *self
+            .http_requests
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+        self.http_durations
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .record(duration_ms);
+    }
+
+    // Formato de exposição em texto do Prometheus
+    pub fn render(&self, processor: &TaskProcessor) -> String {
+        let stats = processor.get_stats();
+        let mut out = String::new();
+
+        describe(
+            &mut out,
+            "task_processor_tasks",
+            "gauge",
+            "Tarefas armazenadas por status, prioridade e tipo",
+        );
+        let mut tasks: Vec<_> = processor
+            .tasks_by_label()
+            .into_iter()
+            .map(|((status, priority, kind), count)| {
+                (
+                    labels(&[
+                        ("status", &label_name(&status)),
+                        ("priority", &label_name(&priority)),
+                        ("kind", &kind),
+                    ]),
+                    count,
+                )
+            })
+            .collect();
+        tasks.sort();
+        for (labels, count) in tasks {
+            sample(&mut out, "task_processor_tasks", &labels, count);
+        }
+
+        describe(
+            &mut out,
+            "task_processor_task_retries",
+            "gauge",
+            "Novas tentativas agendadas após falhas, somadas nas tarefas armazenadas",
+        );
+        sample(&mut out, "task_processor_task_retries", "", stats.retries);
+        describe(
+            &mut out,
+            "task_processor_tasks_timed_out",
+            "gauge",
+            "Tarefas que falharam por exceder o tempo limite",
+        );
+        sample(
+            &mut out,
+            "task_processor_tasks_timed_out",
+            "",
+            stats.timed_out,
+        );
+
+        describe(
+            &mut out,
+            "task_processor_queue_depth",
+            "gauge",
+            "Tarefas prontas aguardando um worker",
+        );
+        sample(
+            &mut out,
+            "task_processor_queue_depth",
+            "",
+            stats.workers.queue_depth,
+        );
+        describe(
+            &mut out,
+            "task_processor_workers_max",
+            "gauge",
+            "Vagas do pool de workers",
+        );
+        sample(
+            &mut out,
+            "task_processor_workers_max",
+            "",
+            stats.workers.max_concurrency,
+        );
+        describe(
+            &mut out,
+            "task_processor_workers_active",
+            "gauge",
+            "Workers executando tarefas, no total e por tipo",
+        );
+        sample(
+            &mut out,
+            "task_processor_workers_active",
+            "",
+            stats.workers.active,
+        );
+        for (kind, workers) in &stats.workers.kinds {
+            sample(
+                &mut out,
+                "task_processor_workers_active",
+                &labels(&[("kind", kind)]),
+                workers.active,
+            );
+        }
+        describe(
+            &mut out,
+            "task_processor_worker_utilization",
+            "gauge",
+            "Fração das vagas do pool em uso",
+        );
+        let utilization = if stats.workers.max_concurrency > 0 {
+            stats.workers.active as f64 / stats.workers.max_concurrency as f64
+        } else {
+            0.0
+        };
+        let _ = writeln!(out, "task_processor_worker_utilization {}", utilization);
+
+        let latency = processor.latency_histograms();
+        task_histograms(
+            &mut out,
+            "task_processor_queue_wait_seconds",
+            "Espera na fila até a primeira execução",
+            latency.queue_wait,
+        );
+        task_histograms(
+            &mut out,
+            "task_processor_run_time_seconds",
+            "Duração de cada execução",
+            latency.run_time,
+        );
+
+        describe(
+            &mut out,
+            "task_processor_websocket_connections",
+            "gauge",
+            "Conexões WebSocket abertas",
+        );
+        sample(
+            &mut out,
+            "task_processor_websocket_connections",
+            "",
+            self.ws_connections.load(Ordering::Relaxed),
+        );
+        describe(
+            &mut out,
+            "task_processor_websocket_lagged_messages_total",
+            "counter",
+            "Atualizações descartadas por clientes WebSocket lentos",
+        );
+        sample(
+            &mut out,
+            "task_processor_websocket_lagged_messages_total",
+            "",
+            self.ws_lagged.load(Ordering::Relaxed),
+        );
+
+        describe(
+            &mut out,
+            "task_processor_http_requests_total",
+            "counter",
+            "Requisições HTTP por método, rota e status",
+        );
+        let requests: BTreeMap<_, _> = self
+            .http_requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((method, route, status), count)| {
+                (
+                    labels(&[
+                        ("method", method),
+                        ("route", route),
+                        ("status", &status.to_string()),
+                    ]),
+                    *count,
+                )
+            })
+            .collect();
+        for (labels, count) in requests {
+            sample(
+                &mut out,
+                "task_processor_http_requests_total",
+                &labels,
+                count,
+            );
+        }
+
+        describe(
+            &mut out,
+            "task_processor_http_request_duration_seconds",
+            "histogram",
+            "Duração das requisições HTTP por método e rota",
+        );
+        let durations: BTreeMap<_, _> = self
+            .http_durations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((method, route), h)| {
+                (labels(&[("method", method), ("route", route)]), h.clone())
+            })
+            .collect();
+        for (labels, h) in durations {
+            histogram(
+                &mut out,
+                "task_processor_http_request_duration_seconds",
+                &labels,
+                &h,
+                &HTTP_BUCKETS,
+            );
+        }
+
+        out
+    }
+}
+
+fn task_histograms(out: &mut String, name: &str, help: &str, series: Vec<LabelledHistogram>) {
+    describe(out, name, "histogram", help);
+    let series: BTreeMap<_, _> = series
+        .into_iter()
+        .map(|(priority, kind, h)| {
+            (
+                labels(&[("priority", &label_name(&priority)), ("kind", &kind)]),
+                h,
+            )
+        })
+        .collect();
+    for (labels, h) in series {
+        histogram(out, name, &labels, &h, &TASK_BUCKETS);
+    }
+}
+
+fn describe(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &str, value: u64) {
+    let _ = writeln!(out, "{}{} {}", name, labels, value);
+}
+
+// Histogramas são guardados em ms; o Prometheus espera segundos
+fn histogram(out: &mut String, name: &str, labels: &str, h: &Histogram, buckets: &[f64]) {
+    let inner = labels.trim_start_matches('{').trim_end_matches('}');
+    let with_le = |le: &str| {
+        if inner.is_empty() {
+            format!("{{le=\"{}\"}}", le)
+        } else {
+            format!("{{{},le=\"{}\"}}", inner, le)
+        }
+    };
+    for le in buckets {
+        let count = h.count_at_most((le * 1000.0) as u64);
+        let _ = writeln!(out, "{}_bucket{} {}", name, with_le(&le.to_string()), count);
+    }
+    let _ = writeln!(out, "{}_bucket{} {}", name, with_le("+Inf"), h.count());
+    let _ = writeln!(out, "{}_sum{} {}", name, labels, h.sum() as f64 / 1000.0);
+    let _ = writeln!(out, "{}_count{} {}", name, labels, h.count());
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn label_name(value: &impl std::fmt::Debug) -> String {
+    format!("{:?}", value).to_lowercase()
+}
+
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(&state.processor),
+    )
+}
+
+// Middleware das rotas: conta requisições e mede a duração por rota (o padrão, não o caminho)
+pub async fn track_http(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "desconhecida".to_string());
+    let started = std::time::Instant::now();
+
+    let response = next.run(request).await;
+    state.metrics.record_http(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed().as_millis() as u64,
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateTaskRequest, TaskPriority};
+
+    #[tokio::test]
+    async fn test_render_exposes_labelled_series() {
+        let processor = TaskProcessor::new();
+        let mut request = CreateTaskRequest::new("Tarefa", 10, TaskPriority::High);
+        request.kind = "simulate".to_string();
+        processor.create_task(request).await.unwrap();
+
+        let metrics = Metrics::new();
+        metrics.ws_connected();
+        metrics.ws_lagged(3);
+        metrics.record_http("GET", "/api/tasks/:id", 200, 4);
+
+        let text = metrics.render(&processor);
+        assert!(text.contains(
+            "task_processor_tasks{status=\"pending\",priority=\"high\",kind=\"simulate\"} 1"
+        ));
+        assert!(text.contains("task_processor_queue_depth 1"));
+        assert!(text.contains("task_processor_websocket_connections 1"));
+        assert!(text.contains("task_processor_websocket_lagged_messages_total 3"));
+        assert!(text.contains(
+            "task_processor_http_requests_total{method=\"GET\",route=\"/api/tasks/:id\",status=\"200\"} 1"
+        ));
+        assert!(text.contains(
+            "task_processor_http_request_duration_seconds_bucket{method=\"GET\",route=\"/api/tasks/:id\",le=\"0.005\"} 1"
+        ));
+        assert!(text.contains(
+            "task_processor_http_request_duration_seconds_count{method=\"GET\",route=\"/api/tasks/:id\"} 1"
+        ));
+    }
+}
@@ -1,18 +1,78 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Mutex;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+use crate::latency::{LatencyHistograms, LatencySeries, LatencyStats, LatencyWindow};
+use crate::registry::DEFAULT_KIND;
+
+// Tenant das tarefas criadas sem autenticação e das gravadas antes da separação por tenant
+pub const DEFAULT_TENANT: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum TaskStatus {
+    // Aguardando a conclusão das tarefas em `depends_on`
+    Blocked,
+    // Aguardando `run_at` para entrar na fila
+    Scheduled,
     Pending,
     Processing,
+    // Falhou e aguarda a próxima tentativa (ver `next_attempt_at`)
+    Retrying,
     Completed,
     Failed,
     Cancelled,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+impl TaskStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled
+        )
+    }
+
+    // Transições permitidas. `Processing -> Pending` é a volta à fila de uma tarefa
+    // interrompida por reinício; `Failed -> Pending`, a devolução a partir da DLQ.
+    pub fn can_transition_to(&self, next: &TaskStatus) -> bool {
+        use TaskStatus::*;
+        matches!(
+            (self, next),
+            (Blocked, Scheduled | Pending | Cancelled)
+                | (Scheduled, Pending | Cancelled)
+                | (Pending, Processing | Cancelled)
+                | (
+                    Processing,
+                    Pending | Retrying | Completed | Failed | Cancelled
+                )
+                | (Retrying, Pending | Cancelled)
+                | (Failed, Pending)
+        )
+    }
+}
+
+// Aceita o nome sem diferenciar maiúsculas (ex.: `?status=pending`)
+impl FromStr for TaskStatus {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "blocked" => Ok(TaskStatus::Blocked),
+            "scheduled" => Ok(TaskStatus::Scheduled),
+            "pending" => Ok(TaskStatus::Pending),
+            "processing" => Ok(TaskStatus::Processing),
+            "retrying" => Ok(TaskStatus::Retrying),
+            "completed" => Ok(TaskStatus::Completed),
+            "failed" => Ok(TaskStatus::Failed),
+            "cancelled" => Ok(TaskStatus::Cancelled),
+            other => Err(format!("Status desconhecido: '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum TaskPriority {
     Low,
@@ -20,159 +80,1284 @@ pub enum TaskPriority {
     High,
 }
 
+impl TaskPriority {
+    fn rank(&self) -> u8 {
+        match self {
+            TaskPriority::Low => 0,
+            TaskPriority::Medium => 1,
+            TaskPriority::High => 2,
+        }
+    }
+}
+
+impl FromStr for TaskPriority {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "low" => Ok(TaskPriority::Low),
+            "medium" => Ok(TaskPriority::Medium),
+            "high" => Ok(TaskPriority::High),
+            other => Err(format!("Prioridade desconhecida: '{}'", other)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: Uuid,
     pub name: String,
+    pub kind: String,
     pub status: TaskStatus,
     pub priority: TaskPriority,
     pub duration_ms: u64,
+    pub payload: serde_json::Value,
+    pub output: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
     pub error_message: Option<String>,
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    // Número da tentativa atual (1 na primeira execução)
+    #[serde(default)]
+    pub attempt: u32,
+    #[serde(default)]
+    pub attempts: Vec<TaskAttempt>,
+    // Novas tentativas já agendadas; as estatísticas somam este campo, então um
+    // reinício reconstrói o total a partir das tarefas
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default)]
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    // Tempo máximo de cada execução; `None` significa sem limite
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub timed_out: bool,
+    // Momento a partir do qual a tarefa pode ser executada
+    #[serde(default)]
+    pub run_at: Option<DateTime<Utc>>,
+    // Tarefas que precisam estar `Completed` antes desta ser executada
+    #[serde(default)]
+    pub depends_on: Vec<Uuid>,
+    #[serde(default)]
+    pub workflow_id: Option<Uuid>,
+    // Tamanho do resultado gravado em disco por exceder o limite inline
+    #[serde(default)]
+    pub output_spilled_bytes: Option<u64>,
+    // Rótulos pesquisáveis na listagem e dados livres do cliente
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+    // Chave enviada pelo cliente para evitar tarefas duplicadas em reenvios
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    // Dono da tarefa, vindo da credencial de quem a criou
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskAttempt {
+    pub attempt: u32,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: DateTime<Utc>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Backoff {
+    Fixed {
+        delay_ms: u64,
+    },
+    Exponential {
+        initial_ms: u64,
+        #[serde(default = "default_multiplier")]
+        multiplier: f64,
+        #[serde(default = "default_max_delay_ms")]
+        max_ms: u64,
+    },
+}
+
+fn default_multiplier() -> f64 {
+    2.0
+}
+
+fn default_max_delay_ms() -> u64 {
+    60_000
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    // Total de execuções permitidas, incluindo a primeira
+    pub max_attempts: u32,
+    pub backoff: Backoff,
+    #[serde(default)]
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("max_attempts deve ser maior que zero".to_string());
+        }
+        if let Backoff::Exponential { multiplier, .. } = self.backoff {
+            if !multiplier.is_finite() || multiplier < 1.0 {
+                return Err("multiplier deve ser maior ou igual a 1".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    // Espera antes da tentativa seguinte a `attempt` (que acabou de falhar)
+    pub fn delay_after(&self, attempt: u32) -> chrono::Duration {
+        let delay_ms = match self.backoff {
+            Backoff::Fixed { delay_ms } => delay_ms,
+            Backoff::Exponential {
+                initial_ms,
+                multiplier,
+                max_ms,
+            } => {
+                let exponent = attempt.saturating_sub(1).min(64) as i32;
+                let delay = initial_ms as f64 * multiplier.powi(exponent);
+                delay.min(max_ms as f64) as u64
+            }
+        };
+
+        // Jitter: sorteia entre metade e o total da espera
+        let delay_ms = if self.jitter && delay_ms > 1 {
+            delay_ms / 2 + rand::random::<u64>() % (delay_ms / 2 + 1)
+        } else {
+            delay_ms
+        };
+        chrono::Duration::milliseconds(delay_ms.min(i64::MAX as u64) as i64)
+    }
 }
 
 impl Task {
-    pub fn new(name: String, duration_ms: u64, priority: TaskPriority) -> Self {
+    pub fn from_request(request: CreateTaskRequest) -> Self {
+        let now = Utc::now();
+        let run_at = request.run_at.or_else(|| {
+            request
+                .delay_ms
+                .map(|ms| now + chrono::Duration::milliseconds(ms.min(i64::MAX as u64) as i64))
+        });
+        let status = match run_at {
+            Some(at) if at > now => TaskStatus::Scheduled,
+            _ => TaskStatus::Pending,
+        };
+
         Self {
             id: Uuid::new_v4(),
-            name,
-            status: TaskStatus::Pending,
-            priority,
-            duration_ms,
-            created_at: Utc::now(),
+            name: request.name,
+            kind: request.kind,
+            status,
+            priority: request.priority,
+            duration_ms: request.duration_ms,
+            payload: request.payload,
+            output: None,
+            created_at: now,
             started_at: None,
             completed_at: None,
             error_message: None,
+            retry_policy: request.retry_policy,
+            attempt: 0,
+            attempts: Vec::new(),
+            retries: 0,
+            next_attempt_at: None,
+            timeout_ms: request.timeout_ms,
+            timed_out: false,
+            run_at,
+            depends_on: request.depends_on,
+            workflow_id: None,
+            output_spilled_bytes: None,
+            labels: request.labels,
+            metadata: request.metadata,
+            idempotency_key: request.idempotency_key,
+            tenant: request.tenant,
         }
     }
 
+    // Dependências concluídas: segue para a fila ou para a espera de `run_at`
+    pub fn unblock(&mut self) {
+        self.status = match self.run_at {
+            Some(at) if at > Utc::now() => TaskStatus::Scheduled,
+            _ => TaskStatus::Pending,
+        };
+    }
+
+    // Cancelamento propagado de uma dependência que não foi concluída
+    pub fn cancel_by_dependency(&mut self, parent: Uuid) {
+        self.mark_as_cancelled();
+        self.error_message = Some(format!("Dependência {} não foi concluída", parent));
+    }
+
     pub fn mark_as_processing(&mut self) {
         self.status = TaskStatus::Processing;
         self.started_at = Some(Utc::now());
+        self.attempt += 1;
+        self.next_attempt_at = None;
     }
 
-    pub fn mark_as_completed(&mut self) {
+    fn record_attempt(&mut self, error: Option<String>) {
+        self.attempts.push(TaskAttempt {
+            attempt: self.attempt,
+            started_at: self.started_at,
+            finished_at: Utc::now(),
+            error,
+        });
+    }
+
+    // Próxima tentativa permitida pela política, se houver
+    pub fn next_retry_at(&self) -> Option<DateTime<Utc>> {
+        let policy = self.retry_policy.as_ref()?;
+        if self.attempt >= policy.max_attempts {
+            return None;
+        }
+        Some(Utc::now() + policy.delay_after(self.attempt))
+    }
+
+    pub fn mark_as_retrying(&mut self, error: String, next_attempt_at: DateTime<Utc>) {
+        self.record_attempt(Some(error.clone()));
+        self.retries += 1;
+        self.status = TaskStatus::Retrying;
+        self.error_message = Some(error);
+        self.next_attempt_at = Some(next_attempt_at);
+    }
+
+    // Tarefa agendada ou aguardando nova tentativa chega a hora de rodar
+    pub fn reset_to_pending(&mut self) {
+        self.status = TaskStatus::Pending;
+        self.started_at = None;
+        self.next_attempt_at = None;
+    }
+
+    // Usado na recuperação: a execução interrompida pelo reinício é descartada
+    // e não conta como tentativa
+    pub fn reset_interrupted(&mut self) {
+        self.reset_to_pending();
+        self.attempt = self.attempt.saturating_sub(1);
+    }
+
+    pub fn mark_as_completed(&mut self, output: serde_json::Value) {
+        self.record_attempt(None);
         self.status = TaskStatus::Completed;
+        self.output = Some(output);
+        self.completed_at = Some(Utc::now());
+    }
+
+    // Resultado grande demais para ficar na tarefa; o conteúdo está em disco
+    pub fn mark_as_completed_spilled(&mut self, size_bytes: u64) {
+        self.record_attempt(None);
+        self.status = TaskStatus::Completed;
+        self.output_spilled_bytes = Some(size_bytes);
         self.completed_at = Some(Utc::now());
     }
 
     pub fn mark_as_failed(&mut self, error: String) {
+        self.record_attempt(Some(error.clone()));
         self.status = TaskStatus::Failed;
         self.completed_at = Some(Utc::now());
         self.error_message = Some(error);
     }
 
+    // Execução interrompida por exceder `timeout_ms`: falha definitiva, sem novas tentativas
+    pub fn mark_as_timed_out(&mut self, timeout_ms: u64) {
+        self.mark_as_failed(format!(
+            "Tempo limite de execução excedido ({} ms)",
+            timeout_ms
+        ));
+        self.timed_out = true;
+    }
+
+    // Nova rodada de tentativas para uma tarefa que saiu da fila de mensagens mortas.
+    // O histórico em `attempts` é preservado.
+    pub fn requeue(&mut self) {
+        self.status = TaskStatus::Pending;
+        self.attempt = 0;
+        self.started_at = None;
+        self.completed_at = None;
+        self.next_attempt_at = None;
+        self.error_message = None;
+        self.output = None;
+        self.output_spilled_bytes = None;
+        self.timed_out = false;
+    }
+
     pub fn mark_as_cancelled(&mut self) {
         self.status = TaskStatus::Cancelled;
         self.completed_at = Some(Utc::now());
+        self.next_attempt_at = None;
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateTaskRequest {
     pub name: String,
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    #[serde(default)]
     pub duration_ms: u64,
     pub priority: TaskPriority,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    // Execução adiada: horário absoluto (UTC) ou espera a partir da criação
+    #[serde(default)]
+    pub run_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+    #[serde(default)]
+    pub depends_on: Vec<Uuid>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+    // Alternativa ao cabeçalho `Idempotency-Key`
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    // Preenchido pela API com o tenant da credencial; o valor enviado pelo cliente é ignorado
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+}
+
+fn default_kind() -> String {
+    DEFAULT_KIND.to_string()
+}
+
+fn default_tenant() -> String {
+    DEFAULT_TENANT.to_string()
+}
+
+impl CreateTaskRequest {
+    #[cfg(test)]
+    pub fn new(name: impl Into<String>, duration_ms: u64, priority: TaskPriority) -> Self {
+        Self {
+            name: name.into(),
+            kind: default_kind(),
+            duration_ms,
+            priority,
+            payload: serde_json::Value::Null,
+            retry_policy: None,
+            timeout_ms: None,
+            run_at: None,
+            delay_ms: None,
+            depends_on: Vec::new(),
+            labels: BTreeMap::new(),
+            metadata: BTreeMap::new(),
+            idempotency_key: None,
+            tenant: default_tenant(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WorkflowStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl WorkflowStatus {
+    pub fn from_tasks(tasks: &[Task]) -> Self {
+        let any = |status: TaskStatus| tasks.iter().any(|t| t.status == status);
+        if any(TaskStatus::Failed) {
+            WorkflowStatus::Failed
+        } else if any(TaskStatus::Cancelled) {
+            WorkflowStatus::Cancelled
+        } else if tasks.iter().all(|t| t.status == TaskStatus::Completed) {
+            WorkflowStatus::Completed
+        } else if tasks.iter().any(|t| {
+            matches!(
+                t.status,
+                TaskStatus::Processing | TaskStatus::Retrying | TaskStatus::Completed
+            )
+        }) {
+            WorkflowStatus::Running
+        } else {
+            WorkflowStatus::Pending
+        }
+    }
+}
+
+// Filtro da listagem de tarefas; listas vazias não restringem
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    // Só tarefas deste tenant; a API sempre restringe ao tenant da credencial
+    pub tenant: Option<String>,
+    pub workflow_id: Option<Uuid>,
+    pub statuses: Vec<TaskStatus>,
+    pub priorities: Vec<TaskPriority>,
+    // Trecho do nome, sem diferenciar maiúsculas
+    pub name: Option<String>,
+    // Rótulos exigidos; sem valor, basta a chave existir
+    pub labels: Vec<(String, Option<String>)>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub completed_after: Option<DateTime<Utc>>,
+    pub completed_before: Option<DateTime<Utc>>,
+}
+
+impl TaskFilter {
+    // Formato: "env:prod,team:dados,urgente"
+    pub fn parse_labels(value: &str) -> Vec<(String, Option<String>)> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once(':') {
+                Some((key, value)) => (key.trim().to_string(), Some(value.trim().to_string())),
+                None => (entry.to_string(), None),
+            })
+            .collect()
+    }
+
+    // Lista separada por vírgulas, ex.: "pending,processing"
+    pub fn parse_list<T: FromStr<Err = String>>(value: &str) -> Result<Vec<T>, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    // Nenhuma condição além do tenant
+    pub fn is_tenant_only(&self) -> bool {
+        self.workflow_id.is_none()
+            && self.statuses.is_empty()
+            && self.priorities.is_empty()
+            && self.name.is_none()
+            && self.labels.is_empty()
+            && self.created_after.is_none()
+            && self.created_before.is_none()
+            && self.completed_after.is_none()
+            && self.completed_before.is_none()
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        self.tenant
+            .as_ref()
+            .is_none_or(|tenant| *tenant == task.tenant)
+            && self
+                .workflow_id
+                .is_none_or(|id| task.workflow_id == Some(id))
+            && (self.statuses.is_empty() || self.statuses.contains(&task.status))
+            && (self.priorities.is_empty() || self.priorities.contains(&task.priority))
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|name| task.name.to_lowercase().contains(&name.to_lowercase()))
+            && self
+                .labels
+                .iter()
+                .all(|(key, value)| match (task.labels.get(key), value) {
+                    (Some(actual), Some(expected)) => actual == expected,
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                })
+            && in_range(
+                Some(task.created_at),
+                self.created_after,
+                self.created_before,
+            )
+            && in_range(
+                task.completed_at,
+                self.completed_after,
+                self.completed_before,
+            )
+    }
+}
+
+// Intervalo `[after, before)`; sem instante, só passa quando não há limites
+fn in_range(
+    at: Option<DateTime<Utc>>,
+    after: Option<DateTime<Utc>>,
+    before: Option<DateTime<Utc>>,
+) -> bool {
+    after.is_none_or(|after| at.is_some_and(|at| at >= after))
+        && before.is_none_or(|before| at.is_some_and(|at| at < before))
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    #[default]
+    CreatedAt,
+    CompletedAt,
+    Priority,
+    Name,
+}
+
+impl SortField {
+    // Mais recentes e mais prioritárias primeiro; nomes em ordem alfabética
+    pub fn default_order(&self) -> SortOrder {
+        match self {
+            SortField::Name => SortOrder::Asc,
+            _ => SortOrder::Desc,
+        }
+    }
+
+    fn key(&self, task: &Task) -> SortKey {
+        let value = match self {
+            SortField::CreatedAt => SortValue::Time(Some(task.created_at)),
+            SortField::CompletedAt => SortValue::Time(task.completed_at),
+            SortField::Priority => SortValue::Rank(task.priority.rank()),
+            SortField::Name => SortValue::Text(task.name.clone()),
+        };
+        (value, task.created_at, task.id)
+    }
+}
+
+impl FromStr for SortField {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "created_at" => Ok(SortField::CreatedAt),
+            "completed_at" => Ok(SortField::CompletedAt),
+            "priority" => Ok(SortField::Priority),
+            "name" => Ok(SortField::Name),
+            other => Err(format!("Campo de ordenação desconhecido: '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            other => Err(format!("Direção de ordenação desconhecida: '{}'", other)),
+        }
+    }
+}
+
+// Valor do campo de ordenação; `created_at` e o id desempatam
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Time(Option<DateTime<Utc>>),
+    Rank(u8),
+    Text(String),
+}
+
+type SortKey = (SortValue, DateTime<Utc>, Uuid);
+
+// Posição da última tarefa de uma página. Trafega como hex do JSON, opaco para o cliente.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Cursor {
+    sort: SortField,
+    key: SortKey,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        serde_json::to_vec(self)
+            .unwrap_or_default()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    pub fn decode(value: &str) -> Result<Self, String> {
+        let invalid = || "Cursor inválido".to_string();
+        if !value.is_ascii() || !value.len().is_multiple_of(2) {
+            return Err(invalid());
+        }
+        let bytes = (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        serde_json::from_slice(&bytes).map_err(|_| invalid())
+    }
+
+    pub fn sort(&self) -> SortField {
+        self.sort
+    }
+
+    // Posição nas ordenações por data: `(data do campo, created_at, id)`
+    pub(crate) fn time_key(&self) -> Option<(Option<DateTime<Utc>>, DateTime<Utc>, Uuid)> {
+        match &self.key {
+            (SortValue::Time(at), created_at, id) => Some((*at, *created_at, *id)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskQuery {
+    pub filter: TaskFilter,
+    pub sort: SortField,
+    pub order: SortOrder,
+    // Continua depois da tarefa indicada; precisa ter sido gerado com o mesmo `sort`
+    pub after: Option<Cursor>,
+    // `None` retorna todas as tarefas restantes
+    pub limit: Option<usize>,
+}
+
+impl Default for TaskQuery {
+    fn default() -> Self {
+        Self {
+            filter: TaskFilter::default(),
+            sort: SortField::default(),
+            order: SortField::default().default_order(),
+            after: None,
+            limit: None,
+        }
+    }
+}
+
+impl TaskQuery {
+    // Ordena as tarefas que passaram no filtro e recorta a página pedida. Usado
+    // quando nenhum índice entrega as tarefas já na ordem pedida.
+    pub fn paginate(&self, tasks: Vec<Task>) -> TaskPage {
+        let total = tasks.len();
+        let mut keyed: Vec<(SortKey, Task)> = tasks
+            .into_iter()
+            .map(|task| (self.sort.key(&task), task))
+            .filter(|(key, _)| {
+                self.after.as_ref().is_none_or(|after| match self.order {
+                    SortOrder::Asc => *key > after.key,
+                    SortOrder::Desc => *key < after.key,
+                })
+            })
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| match self.order {
+            SortOrder::Asc => a.cmp(b),
+            SortOrder::Desc => b.cmp(a),
+        });
+        keyed.truncate(
+            self.limit
+                .map_or(usize::MAX, |limit| limit.saturating_add(1)),
+        );
+        self.page(keyed.into_iter().map(|(_, task)| task).collect(), total)
+    }
+
+    // Recorta a página de tarefas já ordenadas a partir do cursor; basta receber
+    // até `limit + 1` delas para saber se há próxima página
+    pub fn page(&self, mut tasks: Vec<Task>, total: usize) -> TaskPage {
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let next_cursor = (limit > 0 && tasks.len() > limit).then(|| Cursor {
+            sort: self.sort,
+            key: self.sort.key(&tasks[limit - 1]),
+        });
+        tasks.truncate(limit);
+
+        TaskPage {
+            tasks,
+            total,
+            next_cursor,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskPage {
+    pub tasks: Vec<Task>,
+    // Total de tarefas que passam no filtro, somando todas as páginas
+    pub total: usize,
+    pub next_cursor: Option<Cursor>,
+}
+
+// Resposta de `GET /api/tasks/:id/result`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskResult {
+    pub task_id: Uuid,
+    pub status: TaskStatus,
+    pub output: Option<serde_json::Value>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workflow {
+    pub id: Uuid,
+    pub status: WorkflowStatus,
+    pub tasks: Vec<Task>,
+}
+
+// Tarefa de um workflow; `depends_on` referencia as chaves de outras tarefas do mesmo envio
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowTaskRequest {
+    pub key: String,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(flatten)]
+    pub task: CreateTaskRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWorkflowRequest {
+    pub tasks: Vec<WorkflowTaskRequest>,
+}
+
+// Gatilho de um agendamento recorrente
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ScheduleTrigger {
+    // Expressão cron em UTC, com 5 campos ou 6 (segundos primeiro)
+    Cron { expression: String },
+    Interval { every_ms: u64 },
+}
+
+impl ScheduleTrigger {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ScheduleTrigger::Cron { expression } => parse_cron(expression).map(|_| ()),
+            ScheduleTrigger::Interval { every_ms: 0 } => {
+                Err("every_ms deve ser maior que zero".to_string())
+            }
+            ScheduleTrigger::Interval { .. } => Ok(()),
+        }
+    }
+
+    // Primeira execução estritamente depois de `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            ScheduleTrigger::Cron { expression } => {
+                parse_cron(expression).ok()?.after(&after).next()
+            }
+            ScheduleTrigger::Interval { every_ms } => Some(
+                after + chrono::Duration::milliseconds((*every_ms).min(i64::MAX as u64) as i64),
+            ),
+        }
+    }
+}
+
+fn parse_cron(expression: &str) -> Result<cron::Schedule, String> {
+    // O crate `cron` exige o campo de segundos; expressões clássicas rodam no segundo zero
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    expression
+        .parse()
+        .map_err(|e| format!("Expressão cron inválida: {}", e))
+}
+
+// O que fazer quando chega a hora e a tarefa anterior ainda não terminou
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlapPolicy {
+    // Descarta a execução
+    #[default]
+    Skip,
+    // Guarda uma execução para quando a anterior terminar
+    Queue,
+    // Cria a tarefa mesmo assim
+    Allow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleRunOutcome {
+    Spawned,
+    Skipped,
+    Queued,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRun {
+    pub at: DateTime<Utc>,
+    pub outcome: ScheduleRunOutcome,
+    pub task_id: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: Uuid,
+    pub name: String,
+    pub trigger: ScheduleTrigger,
+    pub overlap: OverlapPolicy,
+    // Modelo da tarefa criada a cada execução
+    pub task: CreateTaskRequest,
+    pub paused: bool,
+    pub created_at: DateTime<Utc>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run: Option<ScheduleRun>,
+    // Última tarefa criada; usada para detectar sobreposição
+    pub active_task_id: Option<Uuid>,
+    // Execução guardada pela política `queue`
+    pub queued: bool,
+}
+
+impl Schedule {
+    pub fn from_request(request: CreateScheduleRequest) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name: request.name,
+            next_run_at: (!request.paused)
+                .then(|| request.trigger.next_after(now))
+                .flatten(),
+            trigger: request.trigger,
+            overlap: request.overlap,
+            task: request.task,
+            paused: request.paused,
+            created_at: now,
+            last_run: None,
+            active_task_id: None,
+            queued: false,
+        }
+    }
+
+    // Substitui a definição mantendo identidade e histórico
+    pub fn apply(&mut self, request: CreateScheduleRequest) {
+        let now = Utc::now();
+        self.name = request.name;
+        self.next_run_at = (!request.paused)
+            .then(|| request.trigger.next_after(now))
+            .flatten();
+        self.trigger = request.trigger;
+        self.overlap = request.overlap;
+        self.task = request.task;
+        self.paused = request.paused;
+        self.queued = false;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.next_run_at = None;
+        self.queued = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.next_run_at = self.trigger.next_after(Utc::now());
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateScheduleRequest {
+    pub name: String,
+    pub trigger: ScheduleTrigger,
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+    pub task: CreateTaskRequest,
+    #[serde(default)]
+    pub paused: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskStats {
     pub total_tasks: u64,
+    pub blocked: u64,
+    pub scheduled: u64,
     pub pending: u64,
     pub processing: u64,
     pub completed: u64,
     pub failed: u64,
     pub cancelled: u64,
+    // Tarefas aguardando nova tentativa no momento
+    pub retrying: u64,
+    // Novas tentativas agendadas das tarefas armazenadas (falhas finais contam em `failed`)
+    pub retries: u64,
+    // Falhas por exceder o tempo limite (também contadas em `failed`)
+    pub timed_out: u64,
     pub average_processing_time_ms: f64,
+    pub latency: LatencyStats,
+    pub workers: WorkerStats,
+    // Presentes quando as estatísticas são as de um único tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<TenantQuotaStats>,
+}
+
+// Uso das cotas do tenant; `None` nos limites indica que não há cota
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TenantQuotaStats {
+    pub max_pending: Option<u64>,
+    // Tarefas que contam na cota de pendentes
+    pub waiting: u64,
+    pub max_concurrency: Option<u64>,
+    pub active: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkerStats {
+    pub max_concurrency: u64,
+    pub active: u64,
+    pub queue_depth: u64,
+    pub kinds: BTreeMap<String, KindWorkerStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KindWorkerStats {
+    pub max_concurrency: Option<u64>,
+    pub active: u64,
+}
+
+// Contadores das estatísticas. Ficam atrás de um único lock para que cada
+// transição mova uma tarefa de um status para outro de uma só vez.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Counters {
+    total_tasks: u64,
+    blocked: u64,
+    scheduled: u64,
+    pending: u64,
+    processing: u64,
+    completed: u64,
+    failed: u64,
+    cancelled: u64,
+    retrying: u64,
+    retries: u64,
+    timed_out: u64,
+    total_processing_time_ms: u64,
+    // Tarefas por status, prioridade e tipo, para as métricas rotuladas
+    by_label: HashMap<(TaskStatus, TaskPriority, String), u64>,
+    // Os mesmos contadores separados por tenant (sem `by_label` nem `by_tenant`)
+    by_tenant: HashMap<String, Counters>,
+}
+
+impl Counters {
+    fn status_mut(&mut self, status: &TaskStatus) -> &mut u64 {
+        match status {
+            TaskStatus::Blocked => &mut self.blocked,
+            TaskStatus::Scheduled => &mut self.scheduled,
+            TaskStatus::Pending => &mut self.pending,
+            TaskStatus::Processing => &mut self.processing,
+            TaskStatus::Retrying => &mut self.retrying,
+            TaskStatus::Completed => &mut self.completed,
+            TaskStatus::Failed => &mut self.failed,
+            TaskStatus::Cancelled => &mut self.cancelled,
+        }
+    }
+
+    fn tenant_mut(&mut self, tenant: &str) -> &mut Counters {
+        self.by_tenant.entry(tenant.to_string()).or_default()
+    }
+
+    // Tarefas que ainda não começaram a executar, as que contam na cota de pendentes
+    fn waiting(&self) -> u64 {
+        self.blocked + self.scheduled + self.pending + self.retrying
+    }
+
+    // Soma (ou subtrai) o que a tarefa representa no status em que está
+    fn apply(&mut self, task: &Task, add: bool) {
+        self.count(task, add);
+        self.tenant_mut(&task.tenant).count(task, add);
+        let label = self
+            .by_label
+            .entry((
+                task.status.clone(),
+                task.priority.clone(),
+                task.kind.clone(),
+            ))
+            .or_default();
+        *label = if add {
+            *label + 1
+        } else {
+            label.saturating_sub(1)
+        };
+    }
+
+    fn count(&mut self, task: &Task, add: bool) {
+        let change = |counter: &mut u64, amount: u64| {
+            *counter = if add {
+                *counter + amount
+            } else {
+                counter.saturating_sub(amount)
+            };
+        };
+        change(self.status_mut(&task.status), 1);
+        change(&mut self.retries, task.retries as u64);
+        match task.status {
+            TaskStatus::Completed => {
+                change(
+                    &mut self.total_processing_time_ms,
+                    stored_processing_time(task),
+                );
+            }
+            TaskStatus::Failed if task.timed_out => change(&mut self.timed_out, 1),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Latencies {
+    queue_wait: LatencySeries,
+    run_time: LatencySeries,
+    // As mesmas séries separadas por tenant (sem `by_tenant`)
+    by_tenant: HashMap<String, Latencies>,
+}
+
+impl Latencies {
+    fn stats(&self, window: Option<LatencyWindow>) -> LatencyStats {
+        let now = Utc::now();
+        LatencyStats {
+            window,
+            queue_wait_ms: self.queue_wait.breakdown(window, now),
+            run_time_ms: self.run_time.breakdown(window, now),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Stats {
-    total_tasks: AtomicU64,
-    pending: AtomicU64,
-    processing: AtomicU64,
-    completed: AtomicU64,
-    failed: AtomicU64,
-    cancelled: AtomicU64,
-    total_processing_time_ms: AtomicU64,
-    completed_count: AtomicU64,
+    counters: Mutex<Counters>,
+    latency: Mutex<Latencies>,
 }
 
 impl Stats {
     pub fn new() -> Self {
-        Self {
-            total_tasks: AtomicU64::new(0),
-            pending: AtomicU64::new(0),
-            processing: AtomicU64::new(0),
-            completed: AtomicU64::new(0),
-            failed: AtomicU64::new(0),
-            cancelled: AtomicU64::new(0),
-            total_processing_time_ms: AtomicU64::new(0),
-            completed_count: AtomicU64::new(0),
-        }
+        Self::default()
     }
 
-    pub fn increment_total(&self) {
-        self.total_tasks.fetch_add(1, Ordering::Relaxed);
+    // Contabiliza uma tarefa nova ou carregada do armazenamento na inicialização
+    pub fn add(&self, task: &Task) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.total_tasks += 1;
+        counters.tenant_mut(&task.tenant).total_tasks += 1;
+        counters.apply(task, true);
     }
 
-    pub fn increment_pending(&self) {
-        self.pending.fetch_add(1, Ordering::Relaxed);
+    // Desfaz a contagem de uma tarefa removida do armazenamento, para que as
+    // estatísticas continuem batendo com o que `add` reconstruiria num reinício
+    pub fn remove(&self, task: &Task) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.total_tasks = counters.total_tasks.saturating_sub(1);
+        counters.apply(task, false);
+        let tenant = counters.tenant_mut(&task.tenant);
+        tenant.total_tasks = tenant.total_tasks.saturating_sub(1);
+        if tenant.total_tasks == 0 {
+            counters.by_tenant.remove(&task.tenant);
+        }
     }
 
-    pub fn increment_processing(&self) {
-        self.processing.fetch_add(1, Ordering::Relaxed);
-        self.pending.fetch_sub(1, Ordering::Relaxed);
+    // Única porta de mudança de status: rejeita transições fora da máquina de
+    // estados e move a tarefa entre os contadores numa só operação
+    pub fn transition(&self, before: &Task, after: &Task) -> Result<(), String> {
+        if !before.status.can_transition_to(&after.status) {
+            return Err(format!(
+                "Transição inválida de {:?} para {:?}",
+                before.status, after.status
+            ));
+        }
+        let mut counters = self.counters.lock().unwrap();
+        counters.apply(before, false);
+        counters.apply(after, true);
+        drop(counters);
+        self.record_latency(before, after);
+        Ok(())
     }
 
-    pub fn increment_completed(&self, processing_time_ms: u64) {
-        self.completed.fetch_add(1, Ordering::Relaxed);
-        self.processing.fetch_sub(1, Ordering::Relaxed);
-        self.total_processing_time_ms
-            .fetch_add(processing_time_ms, Ordering::Relaxed);
-        self.completed_count.fetch_add(1, Ordering::Relaxed);
+    // Espera na fila ao iniciar a primeira execução; duração ao terminar cada uma
+    fn record_latency(&self, before: &Task, after: &Task) {
+        let mut latency = self.latency.lock().unwrap();
+        let mut record = |series: fn(&mut Latencies) -> &mut LatencySeries, ms, at| {
+            series(&mut latency).record(&after.priority, &after.kind, ms, at);
+            let tenant = latency.by_tenant.entry(after.tenant.clone()).or_default();
+            series(tenant).record(&after.priority, &after.kind, ms, at);
+        };
+        match (&before.status, &after.status) {
+            (TaskStatus::Pending, TaskStatus::Processing) if before.attempts.is_empty() => {
+                if let Some(started) = after.started_at {
+                    let ready = after
+                        .run_at
+                        .map_or(after.created_at, |at| at.max(after.created_at));
+                    record(|l| &mut l.queue_wait, elapsed_ms(ready, started), started);
+                }
+            }
+            (
+                TaskStatus::Processing,
+                TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Retrying,
+            ) => {
+                if let (Some(started), Some(attempt)) = (before.started_at, after.attempts.last()) {
+                    record(
+                        |l| &mut l.run_time,
+                        elapsed_ms(started, attempt.finished_at),
+                        attempt.finished_at,
+                    );
+                }
+            }
+            _ => {}
+        }
     }
 
-    pub fn increment_failed(&self) {
-        self.failed.fetch_add(1, Ordering::Relaxed);
-        self.processing.fetch_sub(1, Ordering::Relaxed);
+    pub fn tasks_by_label(&self) -> Vec<((TaskStatus, TaskPriority, String), u64)> {
+        let counters = self.counters.lock().unwrap();
+        counters
+            .by_label
+            .iter()
+            .map(|(labels, count)| (labels.clone(), *count))
+            .collect()
     }
 
-    pub fn increment_cancelled(&self) {
-        self.cancelled.fetch_add(1, Ordering::Relaxed);
-        self.processing.fetch_sub(1, Ordering::Relaxed);
+    pub fn latency_histograms(&self) -> LatencyHistograms {
+        let latency = self.latency.lock().unwrap();
+        LatencyHistograms {
+            queue_wait: latency.queue_wait.by_label(),
+            run_time: latency.run_time.by_label(),
+        }
     }
 
-    pub fn decrement_pending(&self) {
-        self.pending.fetch_sub(1, Ordering::Relaxed);
+    pub fn latency(&self, window: Option<LatencyWindow>) -> LatencyStats {
+        self.latency.lock().unwrap().stats(window)
     }
 
     pub fn get_stats(&self) -> TaskStats {
-        let completed_count = self.completed_count.load(Ordering::Relaxed);
-        let total_time = self.total_processing_time_ms.load(Ordering::Relaxed);
-        let avg_time = if completed_count > 0 {
-            total_time as f64 / completed_count as f64
+        let counters = self.counters.lock().unwrap();
+        Self::build(&counters, self.latency(None))
+    }
+
+    // Estatísticas só com as tarefas do tenant
+    pub fn tenant_stats(&self, tenant: &str, window: Option<LatencyWindow>) -> TaskStats {
+        let counters = self.counters.lock().unwrap();
+        let latency = self.latency.lock().unwrap();
+        let mut stats = Self::build(
+            counters
+                .by_tenant
+                .get(tenant)
+                .unwrap_or(&Counters::default()),
+            latency
+                .by_tenant
+                .get(tenant)
+                .unwrap_or(&Latencies::default())
+                .stats(window),
+        );
+        stats.tenant = Some(tenant.to_string());
+        stats
+    }
+
+    // Tarefas do tenant que contam na cota de pendentes
+    pub fn tenant_waiting(&self, tenant: &str) -> u64 {
+        let counters = self.counters.lock().unwrap();
+        counters.by_tenant.get(tenant).map_or(0, Counters::waiting)
+    }
+
+    fn build(counters: &Counters, latency: LatencyStats) -> TaskStats {
+        let avg_time = if counters.completed > 0 {
+            counters.total_processing_time_ms as f64 / counters.completed as f64
         } else {
             0.0
         };
 
         TaskStats {
-            total_tasks: self.total_tasks.load(Ordering::Relaxed),
-            pending: self.pending.load(Ordering::Relaxed),
-            processing: self.processing.load(Ordering::Relaxed),
-            completed: self.completed.load(Ordering::Relaxed),
-            failed: self.failed.load(Ordering::Relaxed),
-            cancelled: self.cancelled.load(Ordering::Relaxed),
+            total_tasks: counters.total_tasks,
+            blocked: counters.blocked,
+            scheduled: counters.scheduled,
+            pending: counters.pending,
+            processing: counters.processing,
+            completed: counters.completed,
+            failed: counters.failed,
+            cancelled: counters.cancelled,
+            retrying: counters.retrying,
+            retries: counters.retries,
+            timed_out: counters.timed_out,
             average_processing_time_ms: avg_time,
+            latency,
+            workers: WorkerStats::default(),
+            tenant: None,
+            quota: None,
         }
     }
 }
 
-impl Default for Stats {
-    fn default() -> Self {
-        Self::new()
+// Tempo de execução registrado na própria tarefa, para que a média seja a mesma
+// calculada ao vivo e na reconstrução após um reinício
+fn stored_processing_time(task: &Task) -> u64 {
+    match (task.started_at, task.completed_at) {
+        (Some(started), Some(completed)) => elapsed_ms(started, completed),
+        _ => 0,
     }
 }
 
+fn elapsed_ms(from: DateTime<Utc>, to: DateTime<Utc>) -> u64 {
+    (to - from).num_milliseconds().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            backoff: Backoff::Exponential {
+                initial_ms: 100,
+                multiplier: 2.0,
+                max_ms: 500,
+            },
+            jitter: false,
+        };
+
+        let delays: Vec<i64> = (1..=5)
+            .map(|attempt| policy.delay_after(attempt).num_milliseconds())
+            .collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+    }
+
+    #[test]
+    fn test_jitter_stays_within_half_and_full_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            backoff: Backoff::Fixed { delay_ms: 1000 },
+            jitter: true,
+        };
+
+        for _ in 0..100 {
+            let delay = policy.delay_after(1).num_milliseconds();
+            assert!((500..=1000).contains(&delay));
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_deserialization() {
+        let policy: RetryPolicy = serde_json::from_value(serde_json::json!({
+            "max_attempts": 4,
+            "backoff": { "type": "exponential", "initial_ms": 250 }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            policy.backoff,
+            Backoff::Exponential {
+                initial_ms: 250,
+                multiplier: 2.0,
+                max_ms: 60_000,
+            }
+        );
+        assert!(!policy.jitter);
+        assert!(policy.validate().is_ok());
+    }
+
+    #[test]
+    fn test_cron_trigger_accepts_five_fields() {
+        let trigger = ScheduleTrigger::Cron {
+            expression: "30 2 * * *".to_string(),
+        };
+        assert!(trigger.validate().is_ok());
+
+        let after = "2024-05-01T03:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let next = trigger.next_after(after).unwrap();
+        assert_eq!(next.to_rfc3339(), "2024-05-02T02:30:00+00:00");
+
+        let invalid = ScheduleTrigger::Cron {
+            expression: "a cada minuto".to_string(),
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+// Resultado pronto para ser gravado na tarefa
+#[derive(Debug)]
+pub enum StoredOutput {
+    Inline(serde_json::Value),
+    // Gravado em `<spill_dir>/<id>.json`; a tarefa guarda só o tamanho
+    Spilled { size_bytes: u64 },
+}
+
+// Aplica o limite de tamanho dos resultados. Com `spill_dir`, resultados grandes vão
+// para disco em vez de falhar a tarefa.
+#[derive(Debug, Clone)]
+pub struct OutputStore {
+    max_inline_bytes: usize,
+    spill_dir: Option<PathBuf>,
+}
+
+impl OutputStore {
+    pub fn new(max_inline_bytes: usize, spill_dir: Option<PathBuf>) -> Self {
+        Self {
+            max_inline_bytes,
+            spill_dir,
+        }
+    }
+
+    pub fn store(&self, id: Uuid, output: serde_json::Value) -> Result<StoredOutput, String> {
+        let bytes = serde_json::to_vec(&output).map_err(|e| e.to_string())?;
+        if bytes.len() <= self.max_inline_bytes {
+            return Ok(StoredOutput::Inline(output));
+        }
+
+        let Some(dir) = &self.spill_dir else {
+            return Err(format!(
+                "Resultado com {} bytes excede o limite de {} bytes",
+                bytes.len(),
+                self.max_inline_bytes
+            ));
+        };
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(self.path(dir, id), &bytes))
+            .map_err(|e| format!("Falha ao gravar resultado em disco: {}", e))?;
+        Ok(StoredOutput::Spilled {
+            size_bytes: bytes.len() as u64,
+        })
+    }
+
+    pub fn load(&self, id: Uuid) -> Result<serde_json::Value, String> {
+        let dir = self
+            .spill_dir
+            .as_ref()
+            .ok_or("Armazenamento de resultados em disco não configurado")?;
+        let bytes = fs::read(self.path(dir, id))
+            .map_err(|e| format!("Falha ao ler resultado em disco: {}", e))?;
+        serde_json::from_slice(&bytes).map_err(|e| e.to_string())
+    }
+
+    // Apaga o resultado gravado em disco, se existir
+    pub fn remove(&self, id: Uuid) -> std::io::Result<()> {
+        let Some(dir) = &self.spill_dir else {
+            return Ok(());
+        };
+        match fs::remove_file(self.path(dir, id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn path(&self, dir: &std::path::Path, id: Uuid) -> PathBuf {
+        dir.join(format!("{}.json", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_small_output_stays_inline() {
+        let outputs = OutputStore::new(64, None);
+        let stored = outputs.store(Uuid::new_v4(), json!({"ok": true})).unwrap();
+        assert!(matches!(stored, StoredOutput::Inline(_)));
+    }
+
+    #[test]
+    fn test_large_output_spills_or_fails() {
+        let big = json!({ "data": "x".repeat(100) });
+
+        let outputs = OutputStore::new(64, None);
+        assert!(outputs.store(Uuid::new_v4(), big.clone()).is_err());
+
+        let dir = std::env::temp_dir().join(format!("outputs-{}", Uuid::new_v4()));
+        let outputs = OutputStore::new(64, Some(dir.clone()));
+        let id = Uuid::new_v4();
+        let stored = outputs.store(id, big.clone()).unwrap();
+        assert!(matches!(stored, StoredOutput::Spilled { size_bytes } if size_bytes > 64));
+        assert_eq!(outputs.load(id).unwrap(), big);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
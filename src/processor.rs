@@ -1,251 +1,3188 @@
-use crate::models::{Stats, Task, TaskPriority, TaskStatus};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tracing::{error, info, warn};
+use crate::config::{ProcessorConfig, RetentionConfig, RetentionPolicy, TenantQuotas};
+use crate::idempotency::{IdempotencyKeys, KeyReservation, Reservation, MAX_KEY_LEN};
+use crate::latency::{LatencyHistograms, LatencyWindow};
+use crate::logs::{LogLine, TaskLogger, TaskLogs};
+use crate::models::{
+    CreateTaskRequest, CreateWorkflowRequest, SortField, SortOrder, Stats, Task, TaskFilter,
+    TaskPage, TaskPriority, TaskQuery, TaskResult, TaskStats, TaskStatus, TenantQuotaStats,
+    Workflow, WorkflowStatus,
+};
+use crate::output::{OutputStore, StoredOutput};
+use crate::queue::{QueueEntry, ReadyQueue};
+use crate::registry::{HandlerRegistry, TaskContext};
+use crate::store::{MemoryStore, TaskStore};
+use crate::timer::TimerQueue;
+use crate::worker::{WorkerPermit, WorkerPool};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, Notify};
+use tokio::task::AbortHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
 use uuid::Uuid;
 
 pub struct TaskProcessor {
-    tasks: Arc<RwLock<HashMap<Uuid, Task>>>,
+    store: Arc<dyn TaskStore>,
+    // Tarefas que falharam definitivamente, fora da coleção principal
+    dead_letters: Arc<dyn TaskStore>,
     stats: Arc<Stats>,
     task_sender: broadcast::Sender<(Uuid, Task)>,
+    registry: HandlerRegistry,
+    pool: WorkerPool,
+    queue: Mutex<ReadyQueue>,
+    wakeup: Arc<Notify>,
+    // Prazos de tarefas agendadas e de novas tentativas
+    timers: Mutex<TimerQueue>,
+    timer_wakeup: Notify,
+    running: Mutex<HashMap<Uuid, RunningTask>>,
+    // Tarefas bloqueadas indexadas por cada dependência ainda não concluída
+    dependents: Mutex<HashMap<Uuid, Vec<Uuid>>>,
+    cancel_grace: tokio::time::Duration,
+    default_timeout_ms: Option<u64>,
+    outputs: OutputStore,
+    // Tarefas criadas com `idempotency_key`, para devolver a original em reenvios
+    idempotency: Mutex<IdempotencyKeys>,
+    tenant_quotas: TenantQuotas,
+    // Vagas da cota de pendentes reservadas por criações ainda não gravadas
+    pending_reserved: Mutex<HashMap<String, usize>>,
+    // Span do ciclo de vida de cada tarefa ativa: aberto ao entrar na fila e
+    // encerrado quando a tarefa termina
+    spans: Mutex<HashMap<Uuid, Span>>,
+    logs: Arc<TaskLogs>,
+}
+
+// Vagas reservadas na cota de pendentes de um tenant; liberadas ao sair do escopo
+struct PendingReservation<'a> {
+    reserved: &'a Mutex<HashMap<String, usize>>,
+    tenant: String,
+    count: usize,
+}
+
+impl Drop for PendingReservation<'_> {
+    fn drop(&mut self) {
+        if self.count == 0 {
+            return;
+        }
+        let mut reserved = self.reserved.lock().unwrap();
+        if let Some(in_flight) = reserved.get_mut(&self.tenant) {
+            *in_flight -= self.count;
+            if *in_flight == 0 {
+                reserved.remove(&self.tenant);
+            }
+        }
+    }
+}
+
+// Resultado de `submit_task`
+#[derive(Debug)]
+pub enum Submission {
+    Created(Task),
+    // Chave de idempotência já usada dentro da janela: tarefa original
+    Existing(Task),
+}
+
+// Motivo da recusa de uma criação
+#[derive(Debug)]
+pub enum SubmitError {
+    Invalid(String),
+    // Cota de tarefas pendentes do tenant esgotada
+    QuotaExceeded(String),
+}
+
+impl std::fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmitError::Invalid(e) | SubmitError::QuotaExceeded(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<String> for SubmitError {
+    fn from(e: String) -> Self {
+        SubmitError::Invalid(e)
+    }
+}
+
+// Controle de uma tarefa em execução, usado para cancelá-la
+struct RunningTask {
+    token: CancellationToken,
+    abort: Option<AbortHandle>,
 }
 
 impl TaskProcessor {
     pub fn new() -> Self {
+        Self::with_registry(HandlerRegistry::with_builtins())
+    }
+
+    pub fn with_registry(registry: HandlerRegistry) -> Self {
+        Self::with_config(ProcessorConfig::default(), registry)
+    }
+
+    pub fn with_config(config: ProcessorConfig, registry: HandlerRegistry) -> Self {
+        Self::with_store(
+            config,
+            registry,
+            Arc::new(MemoryStore::new()),
+            Arc::new(MemoryStore::new()),
+        )
+    }
+
+    pub fn with_store(
+        config: ProcessorConfig,
+        registry: HandlerRegistry,
+        store: Arc<dyn TaskStore>,
+        dead_letters: Arc<dyn TaskStore>,
+    ) -> Self {
         let (task_sender, _) = broadcast::channel(1000);
+        let wakeup = Arc::new(Notify::new());
         Self {
-            tasks: Arc::new(RwLock::new(HashMap::new())),
+            store,
+            dead_letters,
             stats: Arc::new(Stats::new()),
             task_sender,
+            registry,
+            pool: WorkerPool::new(&config, wakeup.clone()),
+            queue: Mutex::new(ReadyQueue::new(
+                config
+                    .queue_aging_ms
+                    .map(|ms| chrono::Duration::milliseconds(ms as i64)),
+            )),
+            wakeup,
+            timers: Mutex::new(TimerQueue::new()),
+            timer_wakeup: Notify::new(),
+            running: Mutex::new(HashMap::new()),
+            dependents: Mutex::new(HashMap::new()),
+            cancel_grace: tokio::time::Duration::from_millis(config.cancel_grace_ms),
+            default_timeout_ms: config.default_timeout_ms,
+            outputs: OutputStore::new(config.output_max_bytes, config.output_spill_dir.clone()),
+            idempotency: Mutex::new(IdempotencyKeys::new(chrono::Duration::milliseconds(
+                config.idempotency_window_ms as i64,
+            ))),
+            tenant_quotas: config.tenant_quotas.clone(),
+            pending_reserved: Mutex::new(HashMap::new()),
+            spans: Mutex::new(HashMap::new()),
+            logs: Arc::new(TaskLogs::new(config.task_log_max_lines)),
+        }
+    }
+
+    pub fn registry(&self) -> &HandlerRegistry {
+        &self.registry
+    }
+
+    // Reconstrói estatísticas e fila a partir do armazenamento após um reinício.
+    // Tarefas que estavam em processamento quando o processo caiu voltam para a fila.
+    pub fn recover(self: &Arc<Self>) -> Result<usize, String> {
+        let mut requeued = 0;
+
+        for task in self.dead_letters.list() {
+            // Queda entre a cópia para a DLQ e a remoção da coleção principal
+            self.store.remove(task.id).map_err(|e| e.to_string())?;
+            self.stats.add(&task);
+            self.remember_idempotency_key(&task);
+        }
+
+        for task in self.store.list() {
+            self.remember_idempotency_key(&task);
+            self.stats.add(&task);
+            let task = if task.status == TaskStatus::Processing {
+                let reset = self.transition(task.id, |t| {
+                    t.reset_interrupted();
+                    true
+                })?;
+                warn!(
+                    "♻️ Tarefa interrompida pelo reinício volta à fila: {}",
+                    task.id
+                );
+                reset.unwrap_or(task)
+            } else {
+                task
+            };
+
+            if !task.status.is_terminal() {
+                self.open_span(&task);
+            }
+            match task.status {
+                TaskStatus::Scheduled => {
+                    let at = task.run_at.unwrap_or_else(chrono::Utc::now);
+                    self.schedule(task.id, at);
+                    requeued += 1;
+                }
+                TaskStatus::Pending => {
+                    self.enqueue(&task);
+                    requeued += 1;
+                }
+                TaskStatus::Retrying => {
+                    let at = task.next_attempt_at.unwrap_or_else(chrono::Utc::now);
+                    self.schedule(task.id, at);
+                    requeued += 1;
+                }
+                TaskStatus::Blocked => self.watch_dependencies(&task),
+                TaskStatus::Failed => self.move_to_dead_letters(task.id),
+                _ => {}
+            }
+        }
+
+        // Dependências podem ter terminado entre a última gravação e a queda
+        let blocked: Vec<Uuid> = self
+            .dependents
+            .lock()
+            .unwrap()
+            .values()
+            .flatten()
+            .copied()
+            .collect();
+        for id in blocked {
+            self.resolve_blocked(id);
+        }
+
+        Ok(requeued)
+    }
+
+    fn enqueue(&self, task: &Task) {
+        // Enfileirar e acordar o despachante
+        self.queue.lock().unwrap().push(QueueEntry::from_task(task));
+        self.wakeup.notify_one();
+    }
+
+    // Validações que não dependem do estado das tarefas; também usadas nos agendamentos
+    pub fn validate_request(&self, request: &CreateTaskRequest) -> Result<(), String> {
+        if !self.registry.contains(&request.kind) {
+            return Err(format!("Tipo de tarefa desconhecido: {}", request.kind));
+        }
+        self.registry
+            .validate_payload(&request.kind, &request.payload)?;
+        if request.labels.keys().any(|key| key.trim().is_empty()) {
+            return Err("Rótulos não podem ter chave vazia".to_string());
+        }
+        if let Some(policy) = &request.retry_policy {
+            policy.validate()?;
+        }
+        if request.timeout_ms == Some(0) {
+            return Err("timeout_ms deve ser maior que zero".to_string());
+        }
+        if request.run_at.is_some() && request.delay_ms.is_some() {
+            return Err("Informe apenas um entre run_at e delay_ms".to_string());
+        }
+        if let Some(key) = &request.idempotency_key {
+            if key.trim().is_empty() || key.len() > MAX_KEY_LEN {
+                return Err(format!(
+                    "Chave de idempotência deve ter entre 1 e {} caracteres",
+                    MAX_KEY_LEN
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn create_task(&self, request: CreateTaskRequest) -> Result<Task, String> {
+        match self.submit_task(request).await.map_err(|e| e.to_string())? {
+            Submission::Created(task) | Submission::Existing(task) => Ok(task),
+        }
+    }
+
+    // Cria a tarefa ou, se a `idempotency_key` já foi usada pelo tenant dentro da
+    // janela, devolve a tarefa original. A chave fica reservada durante a gravação,
+    // para que envios simultâneos com a mesma chave não gerem duplicatas.
+    pub async fn submit_task(&self, request: CreateTaskRequest) -> Result<Submission, SubmitError> {
+        let reservation = match &request.idempotency_key {
+            Some(key) => match self.reserve_key(&request.tenant, key).await {
+                Ok(reservation) => Some(reservation),
+                Err(task) => {
+                    info!("🔁 Reenvio com chave de idempotência já usada: {}", task.id);
+                    return Ok(Submission::Existing(task));
+                }
+            },
+            None => None,
+        };
+
+        let _quota = self.reserve_pending(&request.tenant, 1)?;
+        let task = self.insert_task(request)?;
+        if let Some(reservation) = reservation {
+            reservation.commit(task.id, task.created_at);
+        }
+        Ok(Submission::Created(task))
+    }
+
+    // Reserva a chave do tenant ou devolve a tarefa que já a usou. Se outra
+    // criação com a mesma chave está gravando, espera ela terminar.
+    async fn reserve_key(&self, tenant: &str, key: &str) -> Result<KeyReservation<'_>, Task> {
+        loop {
+            let mut in_flight = {
+                let mut keys = self.idempotency.lock().unwrap();
+                match keys.reserve(tenant, key, chrono::Utc::now()) {
+                    Reservation::Reserved => {
+                        return Ok(KeyReservation::new(&self.idempotency, tenant, key))
+                    }
+                    Reservation::Existing(id) => match self.find_task(id) {
+                        Some(task) => return Err(task),
+                        // A tarefa original já foi removida: a chave fica livre
+                        None => {
+                            keys.forget(tenant, key);
+                            continue;
+                        }
+                    },
+                    Reservation::InFlight(receiver) => receiver,
+                }
+            };
+            // O canal fecha quando a outra criação grava a tarefa ou desiste
+            let _ = in_flight.changed().await;
+        }
+    }
+
+    fn remember_idempotency_key(&self, task: &Task) {
+        if let Some(key) = &task.idempotency_key {
+            self.idempotency.lock().unwrap().insert(
+                task.tenant.clone(),
+                key.clone(),
+                task.id,
+                task.created_at,
+            );
         }
     }
 
-    pub async fn create_task(
+    // Recusa a criação se `count` novas tarefas passarem da cota de pendentes do tenant.
+    // Só vale na criação: devoluções da DLQ e novas tentativas não são barradas.
+    // As vagas ficam reservadas até a reserva sair do escopo, depois de as tarefas
+    // gravadas entrarem nas estatísticas; criações simultâneas não estouram a cota.
+    fn reserve_pending(
         &self,
-        name: String,
-        duration_ms: u64,
-        priority: TaskPriority,
-    ) -> Task {
-        let task = Task::new(name, duration_ms, priority);
+        tenant: &str,
+        count: usize,
+    ) -> Result<PendingReservation<'_>, SubmitError> {
+        let Some(limit) = self.tenant_quotas.pending_limit(tenant) else {
+            return Ok(PendingReservation {
+                reserved: &self.pending_reserved,
+                tenant: tenant.to_string(),
+                count: 0,
+            });
+        };
+        let mut reserved = self.pending_reserved.lock().unwrap();
+        let in_flight = reserved.get(tenant).copied().unwrap_or(0);
+        let waiting = self.stats.tenant_waiting(tenant) as usize + in_flight;
+        if waiting + count > limit {
+            return Err(SubmitError::QuotaExceeded(format!(
+                "Cota de tarefas pendentes esgotada para o tenant {} ({} de {})",
+                tenant, waiting, limit
+            )));
+        }
+        *reserved.entry(tenant.to_string()).or_default() += count;
+        Ok(PendingReservation {
+            reserved: &self.pending_reserved,
+            tenant: tenant.to_string(),
+            count,
+        })
+    }
+
+    fn insert_task(&self, request: CreateTaskRequest) -> Result<Task, String> {
+        let task = self.prepare_task(request)?;
         let task_id = task.id;
 
         // Adicionar à coleção
-        let mut tasks = self.tasks.write().await;
-        tasks.insert(task_id, task.clone());
-        drop(tasks);
+        self.store.insert(task.clone()).map_err(|e| e.to_string())?;
+        self.admit(&task);
+
+        info!("✅ Tarefa criada: {} (ID: {})", task.name, task_id);
+        Ok(task)
+    }
+
+    // Criação em lote: cada item é validado separadamente e os válidos são gravados
+    // de uma vez no armazenamento. Os resultados seguem a ordem dos pedidos; uma
+    // chave de idempotência repetida no próprio lote devolve a tarefa do primeiro item.
+    // Itens além da cota de pendentes do tenant são recusados um a um.
+    pub async fn submit_tasks(
+        &self,
+        requests: Vec<CreateTaskRequest>,
+    ) -> Vec<Result<Submission, String>> {
+        let mut results = Vec::with_capacity(requests.len());
+        let mut batch: Vec<Task> = Vec::new();
+        // Posição em `results` de cada tarefa do lote
+        let mut slots = Vec::new();
+        let mut batch_keys: HashMap<(String, String), usize> = HashMap::new();
+        let mut repeated = Vec::new();
+        // Reservas das chaves e da cota de cada tarefa do lote, na ordem de `batch`;
+        // os itens aceitos recebem o resultado definitivo depois da gravação
+        let mut reservations = Vec::new();
+        let mut quotas = Vec::new();
+
+        // As chaves do lote são reservadas de antemão e em ordem, para que lotes
+        // simultâneos com chaves em comum não fiquem esperando um pelo outro
+        let mut keys = BTreeMap::new();
+        for request in &requests {
+            if let Some(key) = &request.idempotency_key {
+                keys.insert((request.tenant.clone(), key.clone()), None);
+            }
+        }
+        for (tenant_key, slot) in keys.iter_mut() {
+            *slot = Some(
+                self.reserve_key(&tenant_key.0, &tenant_key.1)
+                    .await
+                    .map(Some),
+            );
+        }
+
+        for (i, request) in requests.into_iter().enumerate() {
+            let tenant_key = request
+                .idempotency_key
+                .clone()
+                .map(|key| (request.tenant.clone(), key));
+            let mut reservation = None;
+            if let Some(tenant_key) = &tenant_key {
+                if let Some(&position) = batch_keys.get(tenant_key) {
+                    repeated.push((i, position));
+                    results.push(Err(String::new()));
+                    continue;
+                }
+                match keys.get_mut(tenant_key).and_then(Option::as_mut) {
+                    Some(Err(task)) => {
+                        results.push(Ok(Submission::Existing(task.clone())));
+                        continue;
+                    }
+                    Some(Ok(reserved)) => reservation = reserved.take(),
+                    None => {}
+                }
+            }
+            let prepared = self.prepare_task(request).and_then(|task| {
+                let quota = self
+                    .reserve_pending(&task.tenant, 1)
+                    .map_err(|e| e.to_string())?;
+                Ok((task, quota))
+            });
+            match prepared {
+                Ok((task, quota)) => {
+                    if let Some(key) = &task.idempotency_key {
+                        batch_keys.insert((task.tenant.clone(), key.clone()), batch.len());
+                    }
+                    slots.push(i);
+                    batch.push(task);
+                    reservations.push(reservation);
+                    quotas.push(quota);
+                    results.push(Err(String::new()));
+                }
+                Err(e) => {
+                    // A reserva volta para um próximo item com a mesma chave
+                    if let (Some(tenant_key), Some(reserved)) = (tenant_key, reservation) {
+                        keys.insert(tenant_key, Some(Ok(Some(reserved))));
+                    }
+                    results.push(Err(e));
+                }
+            }
+        }
+
+        if let Err(e) = self.store.insert_many(batch.clone()) {
+            let e = e.to_string();
+            for i in slots
+                .into_iter()
+                .chain(repeated.into_iter().map(|(i, _)| i))
+            {
+                results[i] = Err(e.clone());
+            }
+            return results;
+        }
+
+        for ((task, &i), reservation) in batch.iter().zip(&slots).zip(reservations) {
+            self.admit(task);
+            if let Some(reservation) = reservation {
+                reservation.commit(task.id, task.created_at);
+            }
+            results[i] = Ok(Submission::Created(task.clone()));
+        }
+        drop(quotas);
+        for (i, position) in repeated {
+            results[i] = Ok(Submission::Existing(batch[position].clone()));
+        }
+        info!(
+            "📦 Lote recebido: {} de {} tarefas criadas",
+            batch.len(),
+            results.len()
+        );
+        results
+    }
+
+    // Valida o pedido e monta a tarefa, ainda sem gravá-la
+    fn prepare_task(&self, request: CreateTaskRequest) -> Result<Task, String> {
+        self.validate_request(&request)?;
+        let waiting = self.check_dependencies(&request.tenant, &request.depends_on)?;
+
+        let mut task = self.build_task(request);
+        if waiting {
+            task.status = TaskStatus::Blocked;
+        }
+        Ok(task)
+    }
 
+    fn build_task(&self, request: CreateTaskRequest) -> Task {
+        let mut task = Task::from_request(request);
+        task.timeout_ms = task.timeout_ms.or(self.default_timeout_ms);
+        task
+    }
+
+    // Contabiliza uma tarefa recém-gravada e a encaminha conforme o status inicial
+    fn admit(&self, task: &Task) {
         // Atualizar estatísticas
-        self.stats.increment_total();
-        self.stats.increment_pending();
+        self.stats.add(task);
+        self.open_span(task);
+        match (&task.status, task.run_at) {
+            (TaskStatus::Blocked, _) => self.watch_dependencies(task),
+            (TaskStatus::Scheduled, Some(at)) => self.schedule(task.id, at),
+            _ => self.enqueue(task),
+        }
 
         // Notificar via broadcast
-        let _ = self.task_sender.send((task_id, task.clone()));
+        let _ = self.task_sender.send((task.id, task.clone()));
 
-        info!("✅ Tarefa criada: {} (ID: {})", task.name, task_id);
-        task
+        // Uma dependência pode ter terminado antes do registro acima
+        if task.status == TaskStatus::Blocked {
+            self.resolve_blocked(task.id);
+        }
     }
 
     pub async fn get_task(&self, id: Uuid) -> Option<Task> {
-        let tasks = self.tasks.read().await;
-        tasks.get(&id).cloned()
+        self.store.get(id).or_else(|| self.dead_letters.get(id))
     }
 
-    pub async fn list_tasks(&self) -> Vec<Task> {
-        let tasks = self.tasks.read().await;
-        tasks.values().cloned().collect()
+    pub async fn list_tasks(&self, query: &TaskQuery) -> TaskPage {
+        self.store.query(query)
     }
 
-    pub async fn cancel_task(&self, id: Uuid) -> Result<(), String> {
-        let mut tasks = self.tasks.write().await;
-        
-        if let Some(task) = tasks.get_mut(&id) {
-            match task.status {
+    // Remove uma tarefa finalizada, da coleção principal ou da DLQ
+    pub fn delete_task(&self, id: Uuid) -> Result<Task, String> {
+        let removed = match self.store.get(id) {
+            Some(task) if !task.status.is_terminal() => {
+                return Err("Tarefa ainda não foi finalizada; cancele-a antes".to_string());
+            }
+            Some(_) => self.store.remove(id),
+            None => self.dead_letters.remove(id),
+        };
+        let task = removed
+            .map_err(|e| e.to_string())?
+            .ok_or("Tarefa não encontrada")?;
+
+        self.forget(&task);
+        info!("🗑️ Tarefa {} removida", id);
+        Ok(task)
+    }
+
+    // Limpeza após remover uma tarefa finalizada do armazenamento
+    fn forget(&self, task: &Task) {
+        self.stats.remove(task);
+        self.logs.remove(task.id);
+        if task.output_spilled_bytes.is_some() {
+            if let Err(e) = self.outputs.remove(task.id) {
+                warn!(
+                    "⚠️ Falha ao remover resultado em disco de {}: {}",
+                    task.id, e
+                );
+            }
+        }
+    }
+
+    pub async fn cancel_task(self: &Arc<Self>, id: Uuid) -> Result<(), String> {
+        let mut outcome = Err("Tarefa já foi finalizada".to_string());
+
+        let updated = self.transition(id, |task| {
+            let was_running = match task.status {
+                TaskStatus::Blocked => {
+                    info!("🚫 Tarefa bloqueada cancelada: {}", id);
+                    false
+                }
+                TaskStatus::Scheduled => {
+                    info!("🚫 Tarefa agendada cancelada: {}", id);
+                    false
+                }
                 TaskStatus::Pending => {
-                    task.mark_as_cancelled();
-                    self.stats.increment_cancelled();
-                    self.stats.decrement_pending();
-                    let _ = self.task_sender.send((id, task.clone()));
+                    self.queue.lock().unwrap().remove(id);
                     info!("🚫 Tarefa cancelada: {}", id);
-                    Ok(())
+                    false
                 }
                 TaskStatus::Processing => {
-                    task.mark_as_cancelled();
-                    self.stats.increment_cancelled();
-                    let _ = self.task_sender.send((id, task.clone()));
-                    warn!("⚠️ Tentativa de cancelar tarefa em processamento: {}", id);
-                    Ok(())
+                    info!(
+                        "🛑 Cancelamento solicitado para tarefa em processamento: {}",
+                        id
+                    );
+                    true
+                }
+                TaskStatus::Retrying => {
+                    info!(
+                        "🚫 Tarefa cancelada durante espera de nova tentativa: {}",
+                        id
+                    );
+                    false
+                }
+                _ => return false,
+            };
+            task.mark_as_cancelled();
+            outcome = Ok(was_running);
+            true
+        })?;
+
+        let Some(task) = updated else {
+            if self.dead_letters.get(id).is_some() {
+                return Err("Tarefa já foi finalizada".to_string());
+            }
+            return Err("Tarefa não encontrada".to_string());
+        };
+
+        let was_running = outcome?;
+        let _ = self.task_sender.send((id, task));
+        if was_running {
+            self.interrupt(id);
+        }
+        self.release_dependents(id);
+        Ok(())
+    }
+
+    // Sinaliza o cancelamento ao handler e aborta a execução se o prazo de tolerância expirar
+    fn interrupt(self: &Arc<Self>, id: Uuid) {
+        let Some(token) = self
+            .running
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|running| running.token.clone())
+        else {
+            return;
+        };
+        token.cancel();
+
+        let processor = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(processor.cancel_grace).await;
+            let running = processor.running.lock().unwrap().remove(&id);
+            if let Some(RunningTask {
+                abort: Some(abort), ..
+            }) = running
+            {
+                abort.abort();
+                warn!(
+                    "⚠️ Tarefa {} não encerrou após cancelamento; execução abortada",
+                    id
+                );
+            }
+        });
+    }
+
+    // Aguarda até `timeout` a tarefa chegar a um estado final; retorna o estado mais recente
+    pub async fn wait_for_terminal(
+        &self,
+        id: Uuid,
+        timeout: tokio::time::Duration,
+    ) -> Option<Task> {
+        // Inscrever antes de consultar para não perder a atualização final
+        let mut updates = self.task_sender.subscribe();
+        let mut task = self.get_task(id).await?;
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        while !task.status.is_terminal() {
+            match tokio::time::timeout_at(deadline, updates.recv()).await {
+                Err(_) => break,
+                Ok(Ok((task_id, update))) if task_id == id => task = update,
+                Ok(Ok(_)) => {}
+                Ok(Err(broadcast::error::RecvError::Lagged(_))) => {
+                    task = self.get_task(id).await?;
                 }
-                _ => Err("Tarefa já foi finalizada".to_string()),
+                Ok(Err(broadcast::error::RecvError::Closed)) => break,
             }
-        } else {
-            Err("Tarefa não encontrada".to_string())
         }
+        Some(task)
+    }
+
+    pub fn task_result(&self, task: Task) -> Result<TaskResult, String> {
+        let output = match task.output_spilled_bytes {
+            Some(_) => Some(self.outputs.load(task.id)?),
+            None => task.output,
+        };
+        Ok(TaskResult {
+            task_id: task.id,
+            status: task.status,
+            output,
+            error_message: task.error_message,
+        })
+    }
+
+    pub fn get_stats(&self) -> TaskStats {
+        let mut stats = self.stats.get_stats();
+        stats.workers = self.pool.stats();
+        stats.workers.queue_depth = self.queue.lock().unwrap().len() as u64;
+        stats
+    }
+
+    // Estatísticas só das tarefas do tenant, com o uso das suas cotas; o pool de
+    // workers é compartilhado e aparece inteiro
+    pub fn tenant_stats(&self, tenant: &str, window: Option<LatencyWindow>) -> TaskStats {
+        let mut stats = self.stats.tenant_stats(tenant, window);
+        stats.quota = Some(TenantQuotaStats {
+            max_pending: self.tenant_quotas.pending_limit(tenant).map(|n| n as u64),
+            waiting: stats.blocked + stats.scheduled + stats.pending + stats.retrying,
+            max_concurrency: self
+                .tenant_quotas
+                .concurrency_limit(tenant)
+                .map(|n| n as u64),
+            active: self.pool.tenant_active(tenant) as u64,
+        });
+        stats.workers = self.pool.stats();
+        stats.workers.queue_depth = self.queue.lock().unwrap().len() as u64;
+        stats
     }
 
-    pub fn get_stats(&self) -> crate::models::TaskStats {
-        self.stats.get_stats()
+    pub fn tasks_by_label(&self) -> Vec<((TaskStatus, TaskPriority, String), u64)> {
+        self.stats.tasks_by_label()
+    }
+
+    pub fn latency_histograms(&self) -> LatencyHistograms {
+        self.stats.latency_histograms()
+    }
+
+    // `None` se a tarefa não existe (nem na DLQ)
+    pub fn task_logs(&self, id: Uuid, after: Option<u64>, limit: usize) -> Option<Vec<LogLine>> {
+        self.find_task(id)?;
+        Some(self.logs.read(id, after, limit))
+    }
+
+    pub fn subscribe_logs(&self) -> broadcast::Receiver<(Uuid, LogLine)> {
+        self.logs.subscribe()
     }
 
     pub fn subscribe(&self) -> broadcast::Receiver<(Uuid, Task)> {
         self.task_sender.subscribe()
     }
 
-    pub async fn start_processing(&self) {
+    pub async fn start_processing(self: Arc<Self>) {
         info!("🔄 Iniciando processamento de tarefas...");
-        
+        tokio::join!(self.clone().run_dispatcher(), self.run_timers());
+    }
+
+    async fn run_dispatcher(self: Arc<Self>) {
         loop {
-            // Buscar tarefas pendentes
-            let pending_tasks: Vec<(Uuid, Task)> = {
-                let tasks = self.tasks.read().await;
-                tasks
-                    .iter()
-                    .filter(|(_, task)| matches!(task.status, TaskStatus::Pending))
-                    .map(|(id, task)| (*id, task.clone()))
-                    .collect()
+            // Próxima tarefa pronta cujo tipo e tenant tenham vaga no pool; com o
+            // pool cheio, nem percorre a fila
+            let next = if self.pool.has_capacity() {
+                let mut queue = self.queue.lock().unwrap();
+                queue.pop_first(|entry| self.pool.try_acquire(&entry.kind, &entry.tenant))
+            } else {
+                None
             };
 
-            // Processar tarefas em paralelo
-            let mut handles = Vec::new();
-            
-            for (id, task) in pending_tasks {
-                let tasks = self.tasks.clone();
-                let stats = self.stats.clone();
-                let sender = self.task_sender.clone();
-
-                // Ordenar por prioridade
-                let priority_score = match task.priority {
-                    TaskPriority::High => 3,
-                    TaskPriority::Medium => 2,
-                    TaskPriority::Low => 1,
-                };
+            match next {
+                Some((entry, permit)) => self.dispatch(entry.id, permit),
+                // Aguardar nova tarefa ou vaga liberada
+                None => self.wakeup.notified().await,
+            }
+        }
+    }
 
-                let handle = tokio::spawn(async move {
-                    // Marcar como processando
-                    {
-                        let mut tasks = tasks.write().await;
-                        if let Some(t) = tasks.get_mut(&id) {
-                            t.mark_as_processing();
-                            stats.increment_processing();
-                            let _ = sender.send((id, t.clone()));
-                        }
-                    }
+    fn dispatch(self: &Arc<Self>, id: Uuid, permit: WorkerPermit) {
+        // Marcar como processando antes de liberar o despachante
+        let Some(task) = self.start(id) else {
+            return;
+        };
+
+        let token = CancellationToken::new();
+        self.running.lock().unwrap().insert(
+            id,
+            RunningTask {
+                token: token.clone(),
+                abort: None,
+            },
+        );
+
+        let processor = self.clone();
+        let span = self.task_span(id);
+        let handle = tokio::spawn(
+            async move {
+                let _permit = permit;
+                processor.run_task(task, token).await;
+            }
+            .instrument(span),
+        );
+
+        // A tarefa pode ter terminado antes deste ponto; nesse caso não há o que registrar
+        if let Some(running) = self.running.lock().unwrap().get_mut(&id) {
+            running.abort = Some(handle.abort_handle());
+        }
+    }
+
+    // `None` se a tarefa foi cancelada ou removida enquanto estava na fila
+    fn start(&self, id: Uuid) -> Option<Task> {
+        let mut started = false;
+        let updated = self.transition(id, |t| {
+            started = t.status == TaskStatus::Pending;
+            if started {
+                t.mark_as_processing();
+            }
+            started
+        });
+
+        match updated {
+            Ok(Some(task)) if started => {
+                let _ = self.task_sender.send((id, task.clone()));
+                Some(task)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                error!("❌ Falha ao persistir início da tarefa {}: {}", id, e);
+                None
+            }
+        }
+    }
+
+    async fn run_task(self: &Arc<Self>, task: Task, token: CancellationToken) {
+        let id = task.id;
+        info!("⚙️ Processando tarefa: {} ({})", task.name, task.kind);
+
+        // Executar o handler registrado para o tipo da tarefa, respeitando o tempo limite.
+        // `Err(ms)` indica que a execução excedeu o limite e foi descartada.
+        let timeout_ms = task.timeout_ms;
+        let logger = TaskLogger::new(id, task.attempt, self.logs.clone());
+        let outcome = match self.registry.get(&task.kind) {
+            Some(handler) => {
+                let run = handler.run(TaskContext::new(task, token.clone()).with_logger(logger));
+                match timeout_ms {
+                    Some(ms) => tokio::time::timeout(tokio::time::Duration::from_millis(ms), run)
+                        .await
+                        .map_err(|_| {
+                            token.cancel();
+                            ms
+                        }),
+                    None => Ok(run.await),
+                }
+            }
+            None => Ok(Err("Nenhum handler registrado para o tipo".to_string())),
+        };
+        // Resultado acima do limite vai para disco ou vira falha da execução
+        let outcome = outcome.map(|run| run.and_then(|output| self.outputs.store(id, output)));
+        self.running.lock().unwrap().remove(&id);
+        self.finish(id, outcome);
+    }
 
-                    info!("⚙️ Processando tarefa: {} ({}ms)", task.name, task.duration_ms);
-
-                    // Simular processamento
-                    tokio::time::sleep(tokio::time::Duration::from_millis(task.duration_ms)).await;
-
-                    // Marcar como completada
-                    {
-                        let mut tasks = tasks.write().await;
-                        if let Some(t) = tasks.get_mut(&id) {
-                            if matches!(t.status, TaskStatus::Cancelled) {
-                                return;
-                            }
-                            
-                            // Calcular tempo de processamento
-                            let processing_time = if let Some(started) = t.started_at {
-                                (chrono::Utc::now() - started).num_milliseconds() as u64
-                            } else {
-                                t.duration_ms
-                            };
-
-                            // Simular falha ocasional (5% de chance)
-                            if rand::random::<f64>() < 0.05 {
-                                t.mark_as_failed("Erro aleatório durante processamento".to_string());
-                                stats.increment_failed();
-                                error!("❌ Tarefa falhou: {}", id);
-                            } else {
-                                t.mark_as_completed();
-                                stats.increment_completed(processing_time);
-                                info!("✅ Tarefa completada: {}", id);
-                            }
-                            
-                            let _ = sender.send((id, t.clone()));
+    // Registra o resultado de uma execução: conclusão, nova tentativa ou falha.
+    // `Err(ms)` indica que a execução excedeu o tempo limite.
+    fn finish(&self, id: Uuid, outcome: Result<Result<StoredOutput, String>, u64>) {
+        let spilled = matches!(outcome, Ok(Ok(StoredOutput::Spilled { .. })));
+        let mut result = Some(outcome);
+        let mut retry_at = None;
+        let mut changed = false;
+        let updated = self.transition(id, |t| {
+            // Cancelada durante a execução: o resultado é descartado
+            if t.status != TaskStatus::Processing {
+                return false;
+            }
+
+            match result.take() {
+                Some(Err(timeout_ms)) => {
+                    t.mark_as_timed_out(timeout_ms);
+                    error!(
+                        "⏱️ Tarefa excedeu o tempo limite de {} ms: {}",
+                        timeout_ms, id
+                    );
+                }
+                Some(Ok(Ok(stored))) => {
+                    match stored {
+                        StoredOutput::Inline(output) => t.mark_as_completed(output),
+                        StoredOutput::Spilled { size_bytes } => {
+                            t.mark_as_completed_spilled(size_bytes)
                         }
-                    };
-                });
+                    }
+                    info!("✅ Tarefa completada: {}", id);
+                }
+                Some(Ok(Err(e))) => match t.next_retry_at() {
+                    Some(at) => {
+                        warn!(
+                            "🔁 Tarefa {} falhou na tentativa {}; nova tentativa em {}",
+                            id, t.attempt, at
+                        );
+                        t.mark_as_retrying(e, at);
+                        retry_at = Some(at);
+                    }
+                    None => {
+                        t.mark_as_failed(e);
+                        error!("❌ Tarefa falhou: {}", id);
+                    }
+                },
+                None => return false,
+            }
+            changed = true;
+            true
+        });
 
-                handles.push((priority_score, handle));
+        // Resultado descartado (tarefa cancelada ou falha ao persistir): o arquivo em
+        // disco não pertence a nenhuma tarefa e `forget` nunca o apagaria
+        if spilled && !matches!(&updated, Ok(Some(_)) if changed) {
+            if let Err(e) = self.outputs.remove(id) {
+                warn!("⚠️ Falha ao remover resultado descartado de {}: {}", id, e);
             }
+        }
+        if let Some(at) = retry_at {
+            self.schedule(id, at);
+        }
+        if matches!(&updated, Ok(Some(t)) if changed && t.status == TaskStatus::Failed) {
+            self.move_to_dead_letters(id);
+        }
 
-            // Aguardar todas as tarefas (com prioridade)
-            handles.sort_by(|a, b| b.0.cmp(&a.0));
-            for (_, handle) in handles {
-                let _ = handle.await;
+        match updated {
+            Ok(Some(t)) if changed => {
+                let finished = t.status.is_terminal();
+                let _ = self.task_sender.send((id, t));
+                if finished {
+                    self.release_dependents(id);
+                }
             }
+            Ok(_) => {}
+            Err(e) => error!("❌ Falha ao persistir resultado da tarefa {}: {}", id, e),
+        }
+    }
 
-            // Aguardar um pouco antes da próxima iteração
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    // Aplica `change` à tarefa sob o lock do armazenamento. Se a tarefa mudou
+    // (`change` devolve `true`), a transição é validada e contabilizada junto;
+    // uma transição inválida é desfeita e devolvida como erro.
+    fn transition(
+        &self,
+        id: Uuid,
+        mut change: impl FnMut(&mut Task) -> bool,
+    ) -> Result<Option<Task>, String> {
+        let mut invalid = None;
+        let mut changed = false;
+        let updated = self
+            .store
+            .update(id, &mut |task| {
+                let before = task.clone();
+                if !change(task) {
+                    return false;
+                }
+                if let Err(e) = self.stats.transition(&before, task) {
+                    *task = before;
+                    invalid = Some(e);
+                    return false;
+                }
+                changed = true;
+                true
+            })
+            .map_err(|e| e.to_string())?;
+        if let Some(e) = invalid {
+            return Err(e);
+        }
+        if let Some(task) = updated.as_ref().filter(|_| changed) {
+            self.trace_transition(task);
         }
+        Ok(updated)
     }
 }
 
-impl Default for TaskProcessor {
-    fn default() -> Self {
-        Self::new()
+// Spans do OpenTelemetry por tarefa
+impl TaskProcessor {
+    // O span nasce filho do span corrente: a requisição HTTP que criou a tarefa,
+    // com o trace do `traceparent` recebido, se houver
+    fn open_span(&self, task: &Task) {
+        let span = info_span!(
+            "task",
+            task.id = %task.id,
+            task.name = %task.name,
+            task.kind = %task.kind,
+            task.priority = ?task.priority,
+            task.status = ?task.status,
+        );
+        debug!(parent: &span, status = ?task.status, "📥 Tarefa registrada");
+        self.spans.lock().unwrap().insert(task.id, span);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn task_span(&self, id: Uuid) -> Span {
+        self.spans
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(Span::none)
+    }
 
-    #[tokio::test]
-    async fn test_create_task() {
-        let processor = TaskProcessor::new();
-        let task = processor.create_task(
-            "Test Task".to_string(),
-            1000,
-            TaskPriority::High,
-        ).await;
+    fn trace_transition(&self, task: &Task) {
+        let mut spans = self.spans.lock().unwrap();
+        let Some(span) = spans.get(&task.id) else {
+            return;
+        };
+        span.record("task.status", tracing::field::debug(&task.status));
+        debug!(parent: span, status = ?task.status, attempt = task.attempt, "🔀 Mudança de status");
+        if task.status.is_terminal() {
+            spans.remove(&task.id);
+        }
+    }
+}
 
-        assert_eq!(task.name, "Test Task");
-        assert_eq!(task.status, TaskStatus::Pending);
-        assert_eq!(task.priority, TaskPriority::High);
+// Dependências entre tarefas e workflows (DAG)
+impl TaskProcessor {
+    pub fn find_task(&self, id: Uuid) -> Option<Task> {
+        self.store.get(id).or_else(|| self.dead_letters.get(id))
+    }
 
-        let retrieved = processor.get_task(task.id).await;
-        assert!(retrieved.is_some());
-        assert_eq!(retrieved.unwrap().name, "Test Task");
+    // `Ok(true)` se alguma dependência ainda não terminou; erro se alguma não existe
+    // (ou é de outro tenant) ou já terminou sem ser concluída
+    fn check_dependencies(&self, tenant: &str, depends_on: &[Uuid]) -> Result<bool, String> {
+        let mut waiting = false;
+        for parent in depends_on {
+            let parent_task = self.find_task(*parent).filter(|t| t.tenant == tenant);
+            match parent_task.map(|t| t.status) {
+                None => return Err(format!("Dependência não encontrada: {}", parent)),
+                Some(TaskStatus::Completed) => {}
+                Some(status) if status.is_terminal() => {
+                    return Err(format!("Dependência {} terminou como {:?}", parent, status));
+                }
+                Some(_) => waiting = true,
+            }
+        }
+        Ok(waiting)
     }
 
-    #[tokio::test]
-    async fn test_list_tasks() {
-        let processor = TaskProcessor::new();
-        
-        processor.create_task("Task 1".to_string(), 1000, TaskPriority::Low).await;
-        processor.create_task("Task 2".to_string(), 2000, TaskPriority::Medium).await;
-        
-        let tasks = processor.list_tasks().await;
-        assert_eq!(tasks.len(), 2);
+    fn watch_dependencies(&self, task: &Task) {
+        let mut dependents = self.dependents.lock().unwrap();
+        for parent in &task.depends_on {
+            dependents.entry(*parent).or_default().push(task.id);
+        }
     }
 
-    #[tokio::test]
-    async fn test_cancel_task() {
-        let processor = TaskProcessor::new();
-        let task = processor.create_task(
-            "Task to cancel".to_string(),
-            1000,
-            TaskPriority::Low,
-        ).await;
+    // Uma tarefa terminou: reavalia as tarefas que dependiam dela
+    fn release_dependents(&self, id: Uuid) {
+        let children = self.dependents.lock().unwrap().remove(&id);
+        for child in children.into_iter().flatten() {
+            self.resolve_blocked(child);
+        }
+    }
 
-        let result = processor.cancel_task(task.id).await;
-        assert!(result.is_ok());
+    fn resolve_blocked(&self, id: Uuid) {
+        let Some(task) = self.store.get(id) else {
+            return;
+        };
+        if task.status != TaskStatus::Blocked {
+            return;
+        }
 
-        let cancelled_task = processor.get_task(task.id).await.unwrap();
-        assert_eq!(cancelled_task.status, TaskStatus::Cancelled);
-    }
+        // Primeira dependência que terminou sem ser concluída (ou sumiu), se houver
+        let mut broken = None;
+        let mut waiting = false;
+        for parent in &task.depends_on {
+            match self.find_task(*parent).map(|t| t.status) {
+                Some(TaskStatus::Completed) => {}
+                Some(status) if !status.is_terminal() => waiting = true,
+                _ => {
+                    broken = Some(*parent);
+                    break;
+                }
+            }
+        }
+        if waiting && broken.is_none() {
+            return;
+        }
 
-    #[tokio::test]
-    async fn test_stats() {
-        let processor = TaskProcessor::new();
-        
-        processor.create_task("Task 1".to_string(), 1000, TaskPriority::High).await;
-        processor.create_task("Task 2".to_string(), 2000, TaskPriority::Medium).await;
-        
-        let stats = processor.get_stats();
-        assert_eq!(stats.total_tasks, 2);
-        assert_eq!(stats.pending, 2);
+        let mut changed = false;
+        let updated = self.transition(id, |t| {
+            changed = t.status == TaskStatus::Blocked;
+            if changed {
+                match broken {
+                    Some(parent) => t.cancel_by_dependency(parent),
+                    None => t.unblock(),
+                }
+            }
+            changed
+        });
+
+        let task = match updated {
+            Ok(Some(task)) if changed => task,
+            Ok(_) => return,
+            Err(e) => {
+                error!("❌ Falha ao liberar dependente {}: {}", id, e);
+                return;
+            }
+        };
+        match (&task.status, task.run_at) {
+            (TaskStatus::Cancelled, _) => {
+                warn!(
+                    "🔗 Tarefa {} cancelada: {}",
+                    id,
+                    task.error_message.as_deref().unwrap_or("")
+                )
+            }
+            (TaskStatus::Scheduled, Some(at)) => self.schedule(id, at),
+            _ => {
+                info!("🔗 Dependências concluídas; tarefa {} liberada", id);
+                self.enqueue(&task);
+            }
+        }
+        let cancelled = task.status == TaskStatus::Cancelled;
+        let _ = self.task_sender.send((id, task));
+
+        // O cancelamento se propaga para os dependentes desta tarefa
+        if cancelled {
+            self.release_dependents(id);
+        }
+    }
+
+    // Cria todas as tarefas do workflow ou nenhuma, contando todas na cota de
+    // pendentes do tenant
+    pub fn create_workflow(
+        &self,
+        tenant: &str,
+        mut request: CreateWorkflowRequest,
+    ) -> Result<Workflow, SubmitError> {
+        for item in &mut request.tasks {
+            item.task.tenant = tenant.to_string();
+        }
+        let _quota = self.reserve_pending(tenant, request.tasks.len())?;
+        Ok(self.insert_workflow(request)?)
+    }
+
+    // As dependências são validadas (chaves conhecidas, sem ciclos) antes de qualquer gravação
+    fn insert_workflow(&self, request: CreateWorkflowRequest) -> Result<Workflow, String> {
+        if request.tasks.is_empty() {
+            return Err("O workflow precisa de ao menos uma tarefa".to_string());
+        }
+
+        let mut index = HashMap::new();
+        for (i, item) in request.tasks.iter().enumerate() {
+            if index.insert(item.key.as_str(), i).is_some() {
+                return Err(format!("Chave duplicada no workflow: {}", item.key));
+            }
+        }
+        for item in &request.tasks {
+            self.validate_request(&item.task)
+                .map_err(|e| format!("{}: {}", item.key, e))?;
+            if !item.task.depends_on.is_empty() {
+                return Err(format!(
+                    "{}: use chaves do workflow em depends_on",
+                    item.key
+                ));
+            }
+            if item.task.idempotency_key.is_some() {
+                return Err(format!(
+                    "{}: idempotency_key não é permitida em tarefas de workflow",
+                    item.key
+                ));
+            }
+            for parent in &item.depends_on {
+                if !index.contains_key(parent.as_str()) {
+                    return Err(format!(
+                        "{}: dependência desconhecida: {}",
+                        item.key, parent
+                    ));
+                }
+            }
+        }
+        let order = topological_order(&request, &index)?;
+
+        // Ids definidos antes para que cada tarefa referencie as dependências
+        let workflow_id = Uuid::new_v4();
+        let mut tasks: Vec<Task> = request
+            .tasks
+            .iter()
+            .map(|item| {
+                let mut task = self.build_task(item.task.clone());
+                task.workflow_id = Some(workflow_id);
+                task
+            })
+            .collect();
+        let ids: Vec<Uuid> = tasks.iter().map(|t| t.id).collect();
+        for (task, item) in tasks.iter_mut().zip(&request.tasks) {
+            task.depends_on = item
+                .depends_on
+                .iter()
+                .map(|key| ids[index[key.as_str()]])
+                .collect();
+            if !task.depends_on.is_empty() {
+                task.status = TaskStatus::Blocked;
+            }
+        }
+
+        // Gravar tudo antes de liberar qualquer tarefa; desfazer em caso de erro
+        for (n, &i) in order.iter().enumerate() {
+            if let Err(e) = self.store.insert(tasks[i].clone()) {
+                for &j in &order[..n] {
+                    let _ = self.store.remove(ids[j]);
+                }
+                return Err(e.to_string());
+            }
+        }
+        for &i in &order {
+            self.admit(&tasks[i]);
+        }
+
+        info!(
+            "🕸️ Workflow criado: {} ({} tarefas)",
+            workflow_id,
+            tasks.len()
+        );
+        Ok(Workflow {
+            id: workflow_id,
+            status: WorkflowStatus::from_tasks(&tasks),
+            tasks,
+        })
+    }
+
+    pub fn get_workflow(&self, id: Uuid) -> Option<Workflow> {
+        let query = TaskQuery {
+            filter: TaskFilter {
+                workflow_id: Some(id),
+                ..Default::default()
+            },
+            order: SortOrder::Asc,
+            limit: None,
+            ..Default::default()
+        };
+        let mut tasks = self.store.query(&query).tasks;
+        tasks.extend(self.dead_letters.query(&query).tasks);
+        if tasks.is_empty() {
+            return None;
+        }
+        tasks.sort_by_key(|t| t.created_at);
+
+        Some(Workflow {
+            id,
+            status: WorkflowStatus::from_tasks(&tasks),
+            tasks,
+        })
+    }
+}
+
+// Ordenação topológica (Kahn); sobra de nós não visitados indica ciclo
+fn topological_order(
+    request: &CreateWorkflowRequest,
+    index: &HashMap<&str, usize>,
+) -> Result<Vec<usize>, String> {
+    let n = request.tasks.len();
+    let mut pending_parents = vec![0; n];
+    let mut children = vec![Vec::new(); n];
+    for (i, item) in request.tasks.iter().enumerate() {
+        let parents: HashSet<usize> = item
+            .depends_on
+            .iter()
+            .map(|key| index[key.as_str()])
+            .collect();
+        pending_parents[i] = parents.len();
+        for parent in parents {
+            children[parent].push(i);
+        }
+    }
+
+    let mut ready: VecDeque<usize> = (0..n).filter(|&i| pending_parents[i] == 0).collect();
+    let mut order = Vec::with_capacity(n);
+    while let Some(i) = ready.pop_front() {
+        order.push(i);
+        for &child in &children[i] {
+            pending_parents[child] -= 1;
+            if pending_parents[child] == 0 {
+                ready.push_back(child);
+            }
+        }
+    }
+
+    if order.len() < n {
+        let cycle: Vec<&str> = (0..n)
+            .filter(|&i| pending_parents[i] > 0)
+            .map(|i| request.tasks[i].key.as_str())
+            .collect();
+        return Err(format!("Ciclo de dependências entre: {}", cycle.join(", ")));
+    }
+    Ok(order)
+}
+
+// Fila de mensagens mortas (dead-letter queue)
+impl TaskProcessor {
+    // Copia para a DLQ antes de remover da coleção principal, para não perder a tarefa
+    fn move_to_dead_letters(&self, id: Uuid) {
+        let Some(task) = self.store.get(id) else {
+            return;
+        };
+
+        let moved = self
+            .dead_letters
+            .insert(task)
+            .and_then(|_| self.store.remove(id));
+        match moved {
+            Ok(_) => warn!("🪦 Tarefa {} movida para a fila de mensagens mortas", id),
+            Err(e) => error!("❌ Falha ao mover tarefa {} para a DLQ: {}", id, e),
+        }
+    }
+
+    pub fn list_dead_letters(&self) -> Vec<Task> {
+        let mut tasks = self.dead_letters.list();
+        tasks.sort_by_key(|t| t.completed_at);
+        tasks
+    }
+
+    pub fn find_dead_letters(&self, filter: &TaskFilter) -> Vec<Task> {
+        let query = TaskQuery {
+            filter: filter.clone(),
+            ..Default::default()
+        };
+        self.dead_letters.query(&query).tasks
+    }
+
+    pub fn get_dead_letter(&self, id: Uuid) -> Option<Task> {
+        self.dead_letters.get(id)
+    }
+
+    // Devolve a tarefa à coleção principal com um novo ciclo de tentativas
+    pub fn requeue_dead_letter(&self, id: Uuid) -> Result<Task, String> {
+        let not_found = || "Tarefa não encontrada na fila de mensagens mortas".to_string();
+        let Some(failed) = self.dead_letters.get(id) else {
+            return Err(not_found());
+        };
+        if !failed.status.can_transition_to(&TaskStatus::Pending) {
+            return Err(format!(
+                "Transição inválida de {:?} para {:?}",
+                failed.status,
+                TaskStatus::Pending
+            ));
+        }
+
+        // A tarefa volta à coleção principal ainda falha e só então é retirada
+        // da DLQ; uma queda no meio é resolvida pela recuperação. Se um purge
+        // concorrente a retirou antes, a cópia é desfeita.
+        self.store.insert(failed).map_err(|e| e.to_string())?;
+        match self.dead_letters.remove(id) {
+            Ok(Some(_)) => {}
+            result => {
+                let _ = self.store.remove(id);
+                return Err(result.err().map_or_else(not_found, |e| e.to_string()));
+            }
+        }
+        // A contagem muda junto com a tarefa, como nas demais transições
+        let task = self
+            .transition(id, |t| {
+                t.requeue();
+                true
+            })?
+            .ok_or_else(not_found)?;
+        self.open_span(&task);
+        self.enqueue(&task);
+        let _ = self.task_sender.send((id, task.clone()));
+
+        info!("♻️ Tarefa {} devolvida da DLQ para a fila", id);
+        Ok(task)
+    }
+
+    pub fn purge_dead_letter(&self, id: Uuid) -> Result<Task, String> {
+        match self.dead_letters.remove(id).map_err(|e| e.to_string())? {
+            Some(task) => {
+                self.forget(&task);
+                info!("🗑️ Tarefa {} removida da fila de mensagens mortas", id);
+                Ok(task)
+            }
+            None => Err("Tarefa não encontrada na fila de mensagens mortas".to_string()),
+        }
+    }
+}
+
+// Agendamento: `run_at` e esperas entre tentativas
+impl TaskProcessor {
+    // Registra um prazo no timer; ao vencer, a tarefa agendada ou em espera vai para a fila
+    fn schedule(&self, id: Uuid, at: chrono::DateTime<chrono::Utc>) {
+        self.timers.lock().unwrap().push(at, id);
+        self.timer_wakeup.notify_one();
+    }
+
+    async fn run_timers(self: Arc<Self>) {
+        loop {
+            let (due, next) = {
+                let mut timers = self.timers.lock().unwrap();
+                (timers.pop_due(chrono::Utc::now()), timers.next_deadline())
+            };
+            for id in due {
+                self.promote_due(id);
+            }
+
+            // Dormir até o próximo prazo ou até um novo prazo ser registrado
+            match next {
+                Some(at) => {
+                    let delay = (at - chrono::Utc::now()).to_std().unwrap_or_default();
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = self.timer_wakeup.notified() => {}
+                    }
+                }
+                None => self.timer_wakeup.notified().await,
+            }
+        }
+    }
+
+    // Prazo vencido: a tarefa volta para a fila, a menos que tenha sido cancelada
+    fn promote_due(&self, id: Uuid) {
+        let mut promoted = None;
+        let updated = self.transition(id, |t| {
+            promoted = match t.status {
+                TaskStatus::Scheduled | TaskStatus::Retrying => Some(t.status.clone()),
+                _ => None,
+            };
+            if promoted.is_some() {
+                t.reset_to_pending();
+            }
+            promoted.is_some()
+        });
+
+        match updated {
+            Ok(Some(task)) if promoted.is_some() => {
+                if promoted == Some(TaskStatus::Retrying) {
+                    info!(
+                        "🔁 Tarefa {} de volta à fila (tentativa {})",
+                        id,
+                        task.attempt + 1
+                    );
+                } else {
+                    info!("⏰ Tarefa agendada liberada para execução: {}", id);
+                }
+                self.enqueue(&task);
+                let _ = self.task_sender.send((id, task));
+            }
+            Ok(_) => {}
+            Err(e) => error!("❌ Falha ao liberar tarefa {}: {}", id, e),
+        }
+    }
+}
+
+impl Default for TaskProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Retenção de tarefas finalizadas
+impl TaskProcessor {
+    // Remove as tarefas finalizadas além dos limites de cada status; retorna quantas saíram
+    pub fn purge_expired(
+        &self,
+        retention: &RetentionConfig,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> usize {
+        [
+            (TaskStatus::Completed, &retention.completed),
+            (TaskStatus::Failed, &retention.failed),
+            (TaskStatus::Cancelled, &retention.cancelled),
+        ]
+        .into_iter()
+        .filter(|(_, policy)| policy.is_enabled())
+        .map(|(status, policy)| self.purge_status(status, policy, now))
+        .sum()
+    }
+
+    // As tarefas expiradas são sempre as de `completed_at` mais antigo, então basta
+    // remover as N primeiras nessa ordem, com N vindo do limite mais restritivo
+    fn purge_status(
+        &self,
+        status: TaskStatus,
+        policy: &RetentionPolicy,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> usize {
+        // Tarefas falhas vivem na DLQ
+        let store = match status {
+            TaskStatus::Failed => &self.dead_letters,
+            _ => &self.store,
+        };
+        let filter = TaskFilter {
+            statuses: vec![status],
+            ..Default::default()
+        };
+        let count = |filter: TaskFilter| {
+            store
+                .query(&TaskQuery {
+                    filter,
+                    limit: Some(0),
+                    ..Default::default()
+                })
+                .total
+        };
+
+        let expired = policy.max_age_ms.map_or(0, |ms| {
+            count(TaskFilter {
+                completed_before: Some(now - chrono::Duration::milliseconds(ms as i64)),
+                ..filter.clone()
+            })
+        });
+        let excess = policy
+            .max_count
+            .map_or(0, |max| count(filter.clone()).saturating_sub(max));
+        let purge = expired.max(excess);
+        if purge == 0 {
+            return 0;
+        }
+
+        let oldest = store.query(&TaskQuery {
+            filter,
+            sort: SortField::CompletedAt,
+            order: SortOrder::Asc,
+            limit: Some(purge),
+            ..Default::default()
+        });
+        oldest
+            .tasks
+            .into_iter()
+            .filter(|task| self.delete_task(task.id).is_ok())
+            .count()
+    }
+
+    pub async fn run_retention(self: Arc<Self>, retention: RetentionConfig) {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_millis(retention.interval_ms));
+        loop {
+            interval.tick().await;
+            let purged = self.purge_expired(&retention, chrono::Utc::now());
+            if purged > 0 {
+                info!("🧹 {} tarefas finalizadas removidas pela retenção", purged);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::logs::LogLevel;
+    use crate::models::{
+        Backoff, RetryPolicy, SortField, SortOrder, TaskFilter, TaskPriority, WorkflowTaskRequest,
+        DEFAULT_TENANT,
+    };
+    use crate::registry::{HandlerResult, SimulateHandler, TaskHandler, DEFAULT_KIND};
+    use futures_util::future::BoxFuture;
+
+    pub(crate) const WAIT: tokio::time::Duration = tokio::time::Duration::from_secs(2);
+
+    // Espera `ready` valer, conferindo a cada atualização de tarefa; a vaga do pool
+    // é devolvida depois da última atualização, daí também a conferência periódica
+    pub(crate) async fn wait_until(processor: &TaskProcessor, mut ready: impl FnMut() -> bool) {
+        let mut updates = processor.subscribe();
+        let deadline = tokio::time::Instant::now() + WAIT;
+        while !ready() {
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => panic!("condição não atingida em {:?}", WAIT),
+                _ = updates.recv() => {}
+                _ = tokio::time::sleep(tokio::time::Duration::from_millis(5)) => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_task() {
+        let processor = TaskProcessor::new();
+        let task = processor
+            .create_task(CreateTaskRequest::new(
+                "Test Task",
+                1000,
+                TaskPriority::High,
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(task.name, "Test Task");
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(task.priority, TaskPriority::High);
+
+        let retrieved = processor.get_task(task.id).await;
+        assert!(retrieved.is_some());
+        assert_eq!(retrieved.unwrap().name, "Test Task");
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks() {
+        let processor = TaskProcessor::new();
+
+        processor
+            .create_task(CreateTaskRequest::new("Task 1", 1000, TaskPriority::Low))
+            .await
+            .unwrap();
+        processor
+            .create_task(CreateTaskRequest::new("Task 2", 2000, TaskPriority::Medium))
+            .await
+            .unwrap();
+
+        let tasks = processor.list_tasks(&TaskQuery::default()).await.tasks;
+        assert_eq!(tasks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_task() {
+        let processor = Arc::new(TaskProcessor::new());
+        let task = processor
+            .create_task(CreateTaskRequest::new(
+                "Task to cancel",
+                1000,
+                TaskPriority::Low,
+            ))
+            .await
+            .unwrap();
+
+        let result = processor.cancel_task(task.id).await;
+        assert!(result.is_ok());
+
+        let cancelled_task = processor.get_task(task.id).await.unwrap();
+        assert_eq!(cancelled_task.status, TaskStatus::Cancelled);
+
+        // Cancelar uma tarefa pendente não mexe no contador de processamento
+        let stats = processor.get_stats();
+        assert_eq!(stats.pending, 0);
+        assert_eq!(stats.processing, 0);
+        assert_eq!(stats.cancelled, 1);
+    }
+
+    #[tokio::test]
+    async fn test_illegal_transition_is_rejected() {
+        let processor = TaskProcessor::new();
+        let task = processor
+            .create_task(CreateTaskRequest::new("Task", 10, TaskPriority::Low))
+            .await
+            .unwrap();
+
+        let result = processor.transition(task.id, |t| {
+            t.mark_as_completed(serde_json::Value::Null);
+            true
+        });
+        assert!(result.is_err());
+        assert_eq!(
+            processor.get_task(task.id).await.unwrap().status,
+            TaskStatus::Pending
+        );
+        let stats = processor.get_stats();
+        assert_eq!(stats.pending, 1);
+        assert_eq!(stats.completed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let processor = TaskProcessor::new();
+
+        processor
+            .create_task(CreateTaskRequest::new("Task 1", 1000, TaskPriority::High))
+            .await
+            .unwrap();
+        processor
+            .create_task(CreateTaskRequest::new("Task 2", 2000, TaskPriority::Medium))
+            .await
+            .unwrap();
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.total_tasks, 2);
+        assert_eq!(stats.pending, 2);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_terminal_returns_spilled_output() {
+        let dir = std::env::temp_dir().join(format!("outputs-{}", Uuid::new_v4()));
+        let config = ProcessorConfig {
+            output_max_bytes: 32,
+            output_spill_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        registry.register("payload", PayloadHandler);
+        let processor = Arc::new(TaskProcessor::with_config(config, registry));
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let slow = processor
+            .create_task(CreateTaskRequest::new("Lenta", 200, TaskPriority::Low))
+            .await
+            .unwrap();
+        let mut request = CreateTaskRequest::new("Grande", 0, TaskPriority::High);
+        request.kind = "payload".to_string();
+        request.payload = serde_json::json!({ "data": "x".repeat(64) });
+        let big = processor.create_task(request.clone()).await.unwrap();
+
+        let wait = tokio::time::Duration::from_millis(100);
+        let task = processor.wait_for_terminal(big.id, wait).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert!(task.output.is_none());
+        assert!(task.output_spilled_bytes.unwrap() > 32);
+        let result = processor.task_result(task).unwrap();
+        assert_eq!(result.output, Some(request.payload));
+
+        let task = processor.wait_for_terminal(slow.id, wait).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Processing);
+        assert!(processor
+            .wait_for_terminal(Uuid::new_v4(), wait)
+            .await
+            .is_none());
+        handle.abort();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_run_does_not_leave_spilled_output() {
+        let dir = std::env::temp_dir().join(format!("outputs-{}", Uuid::new_v4()));
+        let config = ProcessorConfig {
+            output_max_bytes: 32,
+            output_spill_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        let mut registry = HandlerRegistry::new();
+        registry.register("tardia", LateOutputHandler);
+        let processor = Arc::new(TaskProcessor::with_config(config, registry));
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let mut request = CreateTaskRequest::new("Grande", 0, TaskPriority::High);
+        request.kind = "tardia".to_string();
+        let task = processor.create_task(request).await.unwrap();
+        wait_until(&processor, || processor.get_stats().workers.active == 1).await;
+
+        // O handler ainda devolve o resultado grande depois do cancelamento
+        processor.cancel_task(task.id).await.unwrap();
+        wait_until(&processor, || processor.get_stats().workers.active == 0).await;
+
+        let task = processor.get_task(task.id).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Cancelled);
+        assert!(task.output_spilled_bytes.is_none());
+        assert!(!dir.join(format!("{}.json", task.id)).exists());
+        handle.abort();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_labels_filter_and_payload_schema() {
+        let mut registry = HandlerRegistry::with_builtins();
+        registry
+            .set_schema(
+                "echo",
+                &serde_json::json!({ "type": "object", "required": ["msg"] }),
+            )
+            .unwrap();
+        let processor = TaskProcessor::with_registry(registry);
+
+        let mut request = CreateTaskRequest::new("Eco", 0, TaskPriority::Low);
+        request.kind = "echo".to_string();
+        request.payload = serde_json::json!({ "outro": 1 });
+        let err = processor.create_task(request.clone()).await.unwrap_err();
+        assert!(err.contains("Payload inválido"), "{}", err);
+
+        request.payload = serde_json::json!({ "msg": "oi" });
+        request.labels = [("env", "prod"), ("team", "dados")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        request
+            .metadata
+            .insert("origem".to_string(), serde_json::json!({ "job": 7 }));
+        let labelled = processor.create_task(request.clone()).await.unwrap();
+        assert_eq!(labelled.metadata["origem"]["job"], 7);
+
+        request.labels.insert("env".to_string(), "dev".to_string());
+        processor.create_task(request).await.unwrap();
+
+        let filter = |value: &str| TaskQuery {
+            filter: TaskFilter {
+                labels: TaskFilter::parse_labels(value),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let found = processor.list_tasks(&filter("env:prod")).await.tasks;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, labelled.id);
+        assert_eq!(processor.list_tasks(&filter("team")).await.total, 2);
+        assert_eq!(
+            processor
+                .list_tasks(&filter("team:dados, env:qa"))
+                .await
+                .total,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_sorts_and_paginates() {
+        let processor = Arc::new(TaskProcessor::new());
+        let mut ids = Vec::new();
+        for (name, priority) in [
+            ("Relatório B", TaskPriority::Low),
+            ("relatório a", TaskPriority::High),
+            ("Backup", TaskPriority::Medium),
+            ("Relatório C", TaskPriority::Medium),
+        ] {
+            let task = processor
+                .create_task(CreateTaskRequest::new(name, 10, priority))
+                .await
+                .unwrap();
+            ids.push(task.id);
+        }
+        processor.cancel_task(ids[2]).await.unwrap();
+
+        // O índice de status acompanha o cancelamento
+        let mut query = TaskQuery {
+            filter: TaskFilter {
+                statuses: TaskFilter::parse_list("pending").unwrap(),
+                name: Some("RELATÓRIO".to_string()),
+                ..Default::default()
+            },
+            sort: SortField::Priority,
+            order: SortOrder::Desc,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = processor.list_tasks(&query).await;
+        assert_eq!(page.total, 3);
+        let names: Vec<_> = page.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["relatório a", "Relatório C"]);
+
+        query.after = page.next_cursor;
+        let page = processor.list_tasks(&query).await;
+        assert_eq!(page.total, 3);
+        assert_eq!(page.tasks.len(), 1);
+        assert_eq!(page.tasks[0].id, ids[0]);
+        assert!(page.next_cursor.is_none());
+
+        let cancelled = TaskQuery {
+            filter: TaskFilter {
+                statuses: vec![TaskStatus::Cancelled],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let page = processor.list_tasks(&cancelled).await;
+        assert_eq!(page.tasks.len(), 1);
+        assert_eq!(page.tasks[0].id, ids[2]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_kind_rejected() {
+        let processor = TaskProcessor::new();
+        let mut request = CreateTaskRequest::new("Task", 1000, TaskPriority::Low);
+        request.kind = "inexistente".to_string();
+
+        assert!(processor.create_task(request).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registered_handler_output() {
+        let mut registry = HandlerRegistry::new();
+        registry.register("payload", PayloadHandler);
+        let processor = Arc::new(TaskProcessor::with_registry(registry));
+
+        let mut request = CreateTaskRequest::new("Payload", 0, TaskPriority::High);
+        request.kind = "payload".to_string();
+        request.payload = serde_json::json!({ "msg": "olá" });
+        let task = processor.create_task(request).await.unwrap();
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let done = processor.wait_for_terminal(task.id, WAIT).await.unwrap();
+        handle.abort();
+
+        assert_eq!(done.status, TaskStatus::Completed);
+        assert_eq!(done.output, Some(serde_json::json!({ "msg": "olá" })));
+    }
+
+    #[tokio::test]
+    async fn test_latency_recorded_per_priority_and_kind() {
+        let processor = TaskProcessor::new();
+        let high = processor
+            .create_task(CreateTaskRequest::new("Alta", 10, TaskPriority::High))
+            .await
+            .unwrap();
+        let low = processor
+            .create_task(CreateTaskRequest::new("Baixa", 10, TaskPriority::Low))
+            .await
+            .unwrap();
+
+        processor.start(high.id).unwrap();
+        processor.start(low.id).unwrap();
+        processor.finish(
+            high.id,
+            Ok(Ok(StoredOutput::Inline(serde_json::Value::Null))),
+        );
+        processor.finish(low.id, Ok(Err("falha".to_string())));
+
+        let latency = processor.get_stats().latency;
+        assert_eq!(latency.window, None);
+        assert_eq!(latency.queue_wait_ms.all.count, 2);
+        assert_eq!(
+            latency.queue_wait_ms.by_priority[&TaskPriority::High].count,
+            1
+        );
+        assert_eq!(
+            latency.queue_wait_ms.by_priority[&TaskPriority::Low].count,
+            1
+        );
+        assert!(!latency
+            .queue_wait_ms
+            .by_priority
+            .contains_key(&TaskPriority::Medium));
+        assert_eq!(latency.run_time_ms.by_kind[DEFAULT_KIND].count, 2);
+
+        // As tarefas sem credencial são do tenant padrão
+        let recent = processor
+            .tenant_stats(DEFAULT_TENANT, Some(LatencyWindow::OneMinute))
+            .latency;
+        assert_eq!(recent.run_time_ms.all.count, 2);
+        assert!(recent.run_time_ms.all.max >= recent.run_time_ms.all.p50);
+    }
+
+    fn tenant_request(name: &str, tenant: &str) -> CreateTaskRequest {
+        let mut request = CreateTaskRequest::new(name, 10, TaskPriority::Low);
+        request.tenant = tenant.to_string();
+        request
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_submissions_respect_keys_and_quota() {
+        let config = ProcessorConfig {
+            tenant_quotas: TenantQuotas {
+                max_pending: Some(5),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let processor = Arc::new(TaskProcessor::with_config(
+            config,
+            HandlerRegistry::with_builtins(),
+        ));
+
+        let submit = |request: CreateTaskRequest| {
+            let processor = processor.clone();
+            tokio::spawn(async move { processor.submit_task(request).await })
+        };
+        let mut keyed = Vec::new();
+        let mut unkeyed = Vec::new();
+        for i in 0..20 {
+            let mut request = tenant_request(&format!("k{}", i), "a");
+            request.idempotency_key = Some("mesma".to_string());
+            keyed.push(submit(request));
+            unkeyed.push(submit(tenant_request(&format!("b{}", i), "b")));
+        }
+
+        let mut ids = HashSet::new();
+        for handle in keyed {
+            match handle.await.unwrap().unwrap() {
+                Submission::Created(task) | Submission::Existing(task) => ids.insert(task.id),
+            };
+        }
+        assert_eq!(ids.len(), 1);
+        let mut created = 0;
+        for handle in unkeyed {
+            match handle.await.unwrap() {
+                Ok(_) => created += 1,
+                Err(e) => assert!(matches!(e, SubmitError::QuotaExceeded(_)), "{}", e),
+            }
+        }
+        assert_eq!(created, 5);
+        assert_eq!(processor.tenant_stats("b", None).total_tasks, 5);
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_isolated_and_pending_quota_is_enforced() {
+        let config = ProcessorConfig {
+            tenant_quotas: TenantQuotas {
+                max_pending: Some(2),
+                pending_limits: HashMap::from([("grande".to_string(), 3)]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let processor = Arc::new(TaskProcessor::with_config(
+            config,
+            HandlerRegistry::with_builtins(),
+        ));
+
+        let first = processor
+            .create_task(tenant_request("a1", "a"))
+            .await
+            .unwrap();
+        processor
+            .create_task(tenant_request("a2", "a"))
+            .await
+            .unwrap();
+        let err = processor
+            .submit_task(tenant_request("a3", "a"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SubmitError::QuotaExceeded(_)), "{}", err);
+
+        // No lote, os itens além da cota falham um a um
+        let results = processor
+            .submit_tasks(vec![
+                tenant_request("b1", "b"),
+                tenant_request("b2", "b"),
+                tenant_request("b3", "b"),
+            ])
+            .await;
+        assert!(results[0].is_ok() && results[1].is_ok());
+        assert!(results[2].as_ref().unwrap_err().contains("Cota"));
+
+        // Limite específico do tenant, com o workflow contado inteiro
+        let workflow = |keys: &[&str]| CreateWorkflowRequest {
+            tasks: keys
+                .iter()
+                .map(|key| WorkflowTaskRequest {
+                    key: key.to_string(),
+                    task: CreateTaskRequest::new(*key, 10, TaskPriority::Low),
+                    depends_on: Vec::new(),
+                })
+                .collect(),
+        };
+        let created = processor
+            .create_workflow("grande", workflow(&["x", "y", "z"]))
+            .unwrap();
+        assert!(created.tasks.iter().all(|task| task.tenant == "grande"));
+        assert!(matches!(
+            processor.create_workflow("grande", workflow(&["w"])),
+            Err(SubmitError::QuotaExceeded(_))
+        ));
+
+        // Dependências e chaves de idempotência não atravessam tenants
+        let mut request = tenant_request("e-dep", "e");
+        request.depends_on = vec![first.id];
+        let err = processor.create_task(request).await.unwrap_err();
+        assert!(err.contains("Dependência não encontrada"), "{}", err);
+        let mut keyed = tenant_request("chave", "c");
+        keyed.idempotency_key = Some("k".to_string());
+        let original = processor.create_task(keyed.clone()).await.unwrap();
+        keyed.tenant = "d".to_string();
+        assert_ne!(processor.create_task(keyed).await.unwrap().id, original.id);
+
+        // Cancelar libera espaço na cota
+        processor.cancel_task(first.id).await.unwrap();
+        processor
+            .create_task(tenant_request("a3", "a"))
+            .await
+            .unwrap();
+
+        let query = TaskQuery {
+            filter: TaskFilter {
+                tenant: Some("a".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let page = processor.list_tasks(&query).await;
+        assert_eq!(page.total, 3);
+        assert!(page.tasks.iter().all(|task| task.tenant == "a"));
+
+        let stats = processor.tenant_stats("a", None);
+        assert_eq!(stats.tenant.as_deref(), Some("a"));
+        assert_eq!(
+            (stats.total_tasks, stats.pending, stats.cancelled),
+            (3, 2, 1)
+        );
+        let quota = stats.quota.unwrap();
+        assert_eq!((quota.max_pending, quota.waiting), (Some(2), 2));
+        assert_eq!(processor.tenant_stats("grande", None).pending, 3);
+        assert_eq!(processor.tenant_stats("ninguem", None).total_tasks, 0);
+        assert_eq!(processor.get_stats().total_tasks, 3 + 2 + 3 + 2);
+    }
+
+    #[tokio::test]
+    async fn test_dispatcher_respects_tenant_concurrency() {
+        let config = ProcessorConfig {
+            tenant_quotas: TenantQuotas {
+                max_concurrency: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        let processor = Arc::new(TaskProcessor::with_config(config, registry));
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let mut ids = Vec::new();
+        for (name, tenant) in [("a1", "a"), ("a2", "a"), ("b1", "b")] {
+            let mut request = tenant_request(name, tenant);
+            request.duration_ms = 200;
+            ids.push(processor.create_task(request).await.unwrap().id);
+        }
+
+        let status = |id| processor.store.get(id).unwrap().status;
+        wait_until(&processor, || {
+            status(ids[0]) == TaskStatus::Processing && status(ids[2]) == TaskStatus::Processing
+        })
+        .await;
+        assert_eq!(status(ids[1]), TaskStatus::Pending);
+        assert_eq!(processor.tenant_stats("a", None).quota.unwrap().active, 1);
+
+        let task = processor.wait_for_terminal(ids[1], WAIT).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_task_span_closes_when_task_finishes() {
+        let processor = Arc::new(TaskProcessor::new());
+        let done = processor
+            .create_task(CreateTaskRequest::new("Concluída", 10, TaskPriority::High))
+            .await
+            .unwrap();
+        let cancelled = processor
+            .create_task(CreateTaskRequest::new("Cancelada", 10, TaskPriority::Low))
+            .await
+            .unwrap();
+        assert_eq!(processor.spans.lock().unwrap().len(), 2);
+
+        processor.start(done.id).unwrap();
+        assert!(processor.spans.lock().unwrap().contains_key(&done.id));
+        processor.finish(
+            done.id,
+            Ok(Ok(StoredOutput::Inline(serde_json::Value::Null))),
+        );
+        processor.cancel_task(cancelled.id).await.unwrap();
+        assert!(processor.spans.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handler_logs_are_captured_per_task() {
+        let mut registry = HandlerRegistry::new();
+        registry.register_fn(
+            "falante",
+            |_: serde_json::Value, ctx: TaskContext| async move {
+                ctx.log(LogLevel::Info, "início");
+                ctx.log(LogLevel::Warn, "quase lá");
+                Ok(())
+            },
+        );
+        let processor = Arc::new(TaskProcessor::with_registry(registry));
+        let mut logs = processor.subscribe_logs();
+
+        let mut request = CreateTaskRequest::new("Falante", 0, TaskPriority::High);
+        request.kind = "falante".to_string();
+        let task = processor.create_task(request).await.unwrap();
+        assert_eq!(processor.task_logs(task.id, None, 100), Some(Vec::new()));
+        assert_eq!(processor.task_logs(Uuid::new_v4(), None, 100), None);
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+        let task = processor
+            .wait_for_terminal(task.id, tokio::time::Duration::from_secs(2))
+            .await
+            .unwrap();
+        handle.abort();
+        assert_eq!(task.status, TaskStatus::Completed);
+
+        let lines = processor.task_logs(task.id, None, 100).unwrap();
+        let messages: Vec<_> = lines.iter().map(|line| line.message.as_str()).collect();
+        assert_eq!(messages, vec!["início", "quase lá"]);
+        assert_eq!(lines[1].level, LogLevel::Warn);
+        assert_eq!(lines[0].attempt, 1);
+        assert_eq!(logs.recv().await.unwrap().0, task.id);
+
+        processor.delete_task(task.id).unwrap();
+        assert_eq!(processor.task_logs(task.id, None, 100), None);
+        assert!(processor.logs.read(task.id, None, 100).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_limits_concurrency() {
+        let config = ProcessorConfig {
+            max_concurrency: 2,
+            ..Default::default()
+        };
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        let processor = Arc::new(TaskProcessor::with_config(config, registry));
+
+        for i in 0..5 {
+            processor
+                .create_task(CreateTaskRequest::new(
+                    format!("Task {}", i),
+                    200,
+                    TaskPriority::Medium,
+                ))
+                .await
+                .unwrap();
+        }
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+        wait_until(&processor, || processor.get_stats().processing == 2).await;
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.pending, 3);
+        assert_eq!(stats.workers.active, 2);
+        assert_eq!(stats.workers.max_concurrency, 2);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_long_task_does_not_block_new_tasks() {
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        let processor = Arc::new(TaskProcessor::with_registry(registry));
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let long = processor
+            .create_task(CreateTaskRequest::new("Longa", 60_000, TaskPriority::High))
+            .await
+            .unwrap();
+        wait_until(&processor, || processor.get_stats().processing == 1).await;
+
+        // Criada depois do despachante estar ocupado com a tarefa longa
+        let short = processor
+            .create_task(CreateTaskRequest::new("Curta", 10, TaskPriority::Low))
+            .await
+            .unwrap();
+
+        let short = processor.wait_for_terminal(short.id, WAIT).await.unwrap();
+        let long = processor.get_task(long.id).await.unwrap();
+        assert_eq!(short.status, TaskStatus::Completed);
+        assert_eq!(long.status, TaskStatus::Processing);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_starts_before_low_under_saturation() {
+        let config = ProcessorConfig {
+            max_concurrency: 1,
+            ..Default::default()
+        };
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        let processor = Arc::new(TaskProcessor::with_config(config, registry));
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        // Ocupa a única vaga enquanto as demais entram na fila
+        processor
+            .create_task(CreateTaskRequest::new("Bloqueio", 50, TaskPriority::Low))
+            .await
+            .unwrap();
+        wait_until(&processor, || processor.get_stats().processing == 1).await;
+
+        let mut ids = Vec::new();
+        for priority in [TaskPriority::Low, TaskPriority::Medium, TaskPriority::High] {
+            let task = processor
+                .create_task(CreateTaskRequest::new("Fila", 10, priority))
+                .await
+                .unwrap();
+            ids.push(task.id);
+        }
+
+        let mut started = Vec::new();
+        for id in ids {
+            let task = processor.wait_for_terminal(id, WAIT).await.unwrap();
+            assert_eq!(task.status, TaskStatus::Completed);
+            started.push(task.started_at.unwrap());
+        }
+        handle.abort();
+        // High < Medium < Low
+        assert!(started[2] < started[1]);
+        assert!(started[1] < started[0]);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_interrupts_running_task() {
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        let processor = Arc::new(TaskProcessor::with_registry(registry));
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let task = processor
+            .create_task(CreateTaskRequest::new("Longa", 60_000, TaskPriority::High))
+            .await
+            .unwrap();
+        wait_until(&processor, || processor.get_stats().workers.active == 1).await;
+
+        processor.cancel_task(task.id).await.unwrap();
+
+        // O handler observou o cancelamento e a vaga foi devolvida
+        wait_until(&processor, || processor.get_stats().workers.active == 0).await;
+        let task = processor.get_task(task.id).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Cancelled);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_aborts_handler_after_grace() {
+        let config = ProcessorConfig {
+            cancel_grace_ms: 30,
+            ..Default::default()
+        };
+        let mut registry = HandlerRegistry::new();
+        registry.register("teimosa", StubbornHandler);
+        let processor = Arc::new(TaskProcessor::with_config(config, registry));
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let mut request = CreateTaskRequest::new("Teimosa", 0, TaskPriority::High);
+        request.kind = "teimosa".to_string();
+        let task = processor.create_task(request).await.unwrap();
+        wait_until(&processor, || processor.get_stats().workers.active == 1).await;
+
+        // O handler ignora o cancelamento e só é abortado depois do prazo
+        let cancelled_at = tokio::time::Instant::now();
+        processor.cancel_task(task.id).await.unwrap();
+        assert_eq!(processor.get_stats().workers.active, 1);
+
+        wait_until(&processor, || processor.get_stats().workers.active == 0).await;
+        assert!(cancelled_at.elapsed() >= tokio::time::Duration::from_millis(30));
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_timeout_fails_hung_task() {
+        let config = ProcessorConfig {
+            default_timeout_ms: Some(5_000),
+            ..Default::default()
+        };
+        let mut registry = HandlerRegistry::new();
+        registry.register("teimosa", StubbornHandler);
+        let processor = Arc::new(TaskProcessor::with_config(config, registry));
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let mut request = CreateTaskRequest::new("Travada", 0, TaskPriority::High);
+        request.kind = "teimosa".to_string();
+        request.timeout_ms = Some(30);
+        // Tempo limite não gera novas tentativas
+        request.retry_policy = Some(RetryPolicy {
+            max_attempts: 3,
+            backoff: Backoff::Fixed { delay_ms: 10 },
+            jitter: false,
+        });
+        let task = processor.create_task(request).await.unwrap();
+
+        let mut request = CreateTaskRequest::new("Padrão", 0, TaskPriority::Low);
+        request.kind = "teimosa".to_string();
+        let default = processor.create_task(request).await.unwrap();
+        assert_eq!(default.timeout_ms, Some(5_000));
+
+        let task = processor.wait_for_terminal(task.id, WAIT).await.unwrap();
+        wait_until(&processor, || processor.get_stats().workers.active == 1).await;
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert!(task.timed_out);
+        assert!(task.error_message.unwrap().contains("Tempo limite"));
+        assert_eq!(task.attempts.len(), 1);
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.timed_out, 1);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.retries, 0);
+        assert_eq!(stats.workers.active, 1);
+        handle.abort();
+    }
+
+    // Ignora o cancelamento cooperativo
+    struct StubbornHandler;
+
+    impl TaskHandler for StubbornHandler {
+        fn run(&self, _ctx: TaskContext) -> BoxFuture<'static, HandlerResult> {
+            Box::pin(async move {
+                tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+                Ok(serde_json::Value::Null)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recover_requeues_interrupted_tasks() {
+        let store: Arc<dyn TaskStore> = Arc::new(MemoryStore::new());
+        let mut interrupted = Task::from_request(CreateTaskRequest::new(
+            "Interrompida",
+            10,
+            TaskPriority::High,
+        ));
+        interrupted.mark_as_processing();
+        let mut done =
+            Task::from_request(CreateTaskRequest::new("Concluída", 10, TaskPriority::Low));
+        done.mark_as_processing();
+        done.mark_as_completed(serde_json::Value::Null);
+        store.insert(interrupted.clone()).unwrap();
+        store.insert(done.clone()).unwrap();
+
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        let processor = Arc::new(TaskProcessor::with_store(
+            ProcessorConfig::default(),
+            registry,
+            store,
+            Arc::new(MemoryStore::new()),
+        ));
+        assert_eq!(processor.recover().unwrap(), 1);
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.total_tasks, 2);
+        assert_eq!(stats.pending, 1);
+        assert_eq!(stats.completed, 1);
+        assert_eq!(stats.workers.queue_depth, 1);
+        // A execução interrompida não conta como tentativa
+        let task = processor.get_task(interrupted.id).await.unwrap();
+        assert_eq!(task.attempt, 0);
+        assert!(task.started_at.is_none());
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+        let task = processor
+            .wait_for_terminal(interrupted.id, WAIT)
+            .await
+            .unwrap();
+        handle.abort();
+
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert_eq!(task.attempt, 1);
+    }
+
+    #[tokio::test]
+    async fn test_delayed_task_waits_for_run_at() {
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        let processor = Arc::new(TaskProcessor::with_registry(registry));
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let mut request = CreateTaskRequest::new("Adiada", 10, TaskPriority::High);
+        request.delay_ms = Some(60);
+        let delayed = processor.create_task(request).await.unwrap();
+        assert_eq!(delayed.status, TaskStatus::Scheduled);
+
+        let mut request = CreateTaskRequest::new("Cancelada", 10, TaskPriority::High);
+        request.run_at = Some(chrono::Utc::now() + chrono::Duration::milliseconds(30));
+        let cancelled = processor.create_task(request).await.unwrap();
+        processor.cancel_task(cancelled.id).await.unwrap();
+
+        let mut request = CreateTaskRequest::new("Imediata", 10, TaskPriority::Low);
+        request.run_at = Some(chrono::Utc::now() - chrono::Duration::seconds(1));
+        assert_eq!(
+            processor.create_task(request).await.unwrap().status,
+            TaskStatus::Pending
+        );
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.scheduled, 1);
+        assert_eq!(stats.cancelled, 1);
+
+        // Só começa quando chega a hora marcada
+        let task = processor.wait_for_terminal(delayed.id, WAIT).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert!(task.started_at.unwrap() >= delayed.run_at.unwrap());
+        let task = processor.get_task(cancelled.id).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Cancelled);
+
+        wait_until(&processor, || processor.get_stats().completed == 2).await;
+        assert_eq!(processor.get_stats().scheduled, 0);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_returns_original_task() {
+        let store: Arc<dyn TaskStore> = Arc::new(MemoryStore::new());
+        let open = |config: ProcessorConfig| {
+            Arc::new(TaskProcessor::with_store(
+                config,
+                HandlerRegistry::with_builtins(),
+                store.clone(),
+                Arc::new(MemoryStore::new()),
+            ))
+        };
+        let mut request = CreateTaskRequest::new("Pagamento", 10, TaskPriority::High);
+        request.idempotency_key = Some("pedido-42".to_string());
+
+        let processor = open(ProcessorConfig::default());
+        let Submission::Created(original) = processor.submit_task(request.clone()).await.unwrap()
+        else {
+            panic!("primeiro envio deveria criar a tarefa");
+        };
+        let again = processor.submit_task(request.clone()).await.unwrap();
+        assert!(matches!(again, Submission::Existing(task) if task.id == original.id));
+        assert_eq!(processor.get_stats().total_tasks, 1);
+
+        // A chave sobrevive ao reinício, reconstruída a partir do armazenamento
+        let restarted = open(ProcessorConfig::default());
+        restarted.recover().unwrap();
+        let again = restarted.submit_task(request.clone()).await.unwrap();
+        assert!(matches!(again, Submission::Existing(task) if task.id == original.id));
+
+        // Fora da janela, a mesma chave cria outra tarefa
+        let expired = open(ProcessorConfig {
+            idempotency_window_ms: 0,
+            ..Default::default()
+        });
+        expired.recover().unwrap();
+        let again = expired.submit_task(request).await.unwrap();
+        assert!(matches!(again, Submission::Created(task) if task.id != original.id));
+    }
+
+    #[tokio::test]
+    async fn test_batch_submission_reports_each_item() {
+        let processor = TaskProcessor::new();
+        let mut keyed = CreateTaskRequest::new("Com chave", 10, TaskPriority::Low);
+        keyed.idempotency_key = Some("lote-1".to_string());
+        let mut unknown = CreateTaskRequest::new("Tipo errado", 10, TaskPriority::Low);
+        unknown.kind = "inexistente".to_string();
+
+        let results = processor
+            .submit_tasks(vec![
+                CreateTaskRequest::new("A", 10, TaskPriority::High),
+                keyed.clone(),
+                unknown,
+                keyed,
+            ])
+            .await;
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[0], Ok(Submission::Created(_))));
+        let Ok(Submission::Created(first)) = &results[1] else {
+            panic!("item com chave deveria ser criado");
+        };
+        assert!(results[2]
+            .as_ref()
+            .is_err_and(|e| e.contains("inexistente")));
+        assert!(matches!(&results[3], Ok(Submission::Existing(task)) if task.id == first.id));
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.total_tasks, 2);
+        assert_eq!(stats.pending, 2);
+        assert_eq!(stats.workers.queue_depth, 2);
+    }
+
+    #[tokio::test]
+    async fn test_delete_only_finished_tasks() {
+        let processor = Arc::new(TaskProcessor::new());
+        let task = processor
+            .create_task(CreateTaskRequest::new("Apagar", 10, TaskPriority::Low))
+            .await
+            .unwrap();
+        assert!(processor.delete_task(task.id).is_err());
+
+        processor.cancel_task(task.id).await.unwrap();
+        assert_eq!(processor.get_stats().cancelled, 1);
+        processor.delete_task(task.id).unwrap();
+        assert!(processor.get_task(task.id).await.is_none());
+        assert!(processor.delete_task(task.id).is_err());
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.total_tasks, 0);
+        assert_eq!(stats.cancelled, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retention_purges_oldest_and_keeps_stats_consistent() {
+        let store: Arc<dyn TaskStore> = Arc::new(MemoryStore::new());
+        let now = chrono::Utc::now();
+        let finished = |minutes_ago: i64, cancelled: bool| {
+            let mut task =
+                Task::from_request(CreateTaskRequest::new("Antiga", 10, TaskPriority::Low));
+            task.mark_as_processing();
+            if cancelled {
+                task.mark_as_cancelled();
+            } else {
+                task.mark_as_completed(serde_json::Value::Null);
+            }
+            task.completed_at = Some(now - chrono::Duration::minutes(minutes_ago));
+            task
+        };
+        let completed: Vec<Task> = (0..5).map(|i| finished(i * 10, false)).collect();
+        for task in &completed {
+            store.insert(task.clone()).unwrap();
+        }
+        store.insert(finished(60, true)).unwrap();
+        store.insert(finished(1, true)).unwrap();
+
+        let open = || {
+            let processor = Arc::new(TaskProcessor::with_store(
+                ProcessorConfig::default(),
+                HandlerRegistry::with_builtins(),
+                store.clone(),
+                Arc::new(MemoryStore::new()),
+            ));
+            processor.recover().unwrap();
+            processor
+        };
+        let processor = open();
+        let retention = RetentionConfig {
+            completed: RetentionPolicy {
+                max_age_ms: Some(35 * 60 * 1000),
+                max_count: Some(3),
+            },
+            cancelled: RetentionPolicy {
+                max_age_ms: Some(30 * 60 * 1000),
+                max_count: None,
+            },
+            ..Default::default()
+        };
+        assert_eq!(processor.purge_expired(&retention, now), 3);
+        assert_eq!(processor.purge_expired(&retention, now), 0);
+
+        // As duas concluídas mais antigas saem pelo limite de quantidade
+        assert!(processor.get_task(completed[4].id).await.is_none());
+        assert!(processor.get_task(completed[3].id).await.is_none());
+        assert!(processor.get_task(completed[2].id).await.is_some());
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.total_tasks, 4);
+        assert_eq!(stats.completed, 3);
+        assert_eq!(stats.cancelled, 1);
+        let restored = open().get_stats();
+        assert_eq!(restored.total_tasks, stats.total_tasks);
+        assert_eq!(restored.completed, stats.completed);
+        assert_eq!(
+            restored.average_processing_time_ms,
+            stats.average_processing_time_ms
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recover_reschedules_scheduled_tasks() {
+        let store: Arc<dyn TaskStore> = Arc::new(MemoryStore::new());
+        let mut request = CreateTaskRequest::new("Agendada", 10, TaskPriority::Medium);
+        request.delay_ms = Some(40);
+        let scheduled = Task::from_request(request);
+        store.insert(scheduled.clone()).unwrap();
+
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        let processor = Arc::new(TaskProcessor::with_store(
+            ProcessorConfig::default(),
+            registry,
+            store,
+            Arc::new(MemoryStore::new()),
+        ));
+        assert_eq!(processor.recover().unwrap(), 1);
+        assert_eq!(processor.get_stats().scheduled, 1);
+        assert_eq!(processor.get_stats().workers.queue_depth, 0);
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+        let task = processor
+            .wait_for_terminal(scheduled.id, WAIT)
+            .await
+            .unwrap();
+        handle.abort();
+
+        assert_eq!(task.status, TaskStatus::Completed);
+    }
+
+    fn simulate_processor() -> Arc<TaskProcessor> {
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        registry.register("instavel", FlakyHandler::new(1));
+        Arc::new(TaskProcessor::with_registry(registry))
+    }
+
+    #[tokio::test]
+    async fn test_dependent_task_waits_for_parent() {
+        let processor = simulate_processor();
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let parent = processor
+            .create_task(CreateTaskRequest::new("Pai", 40, TaskPriority::Low))
+            .await
+            .unwrap();
+        let mut request = CreateTaskRequest::new("Filha", 10, TaskPriority::High);
+        request.depends_on = vec![parent.id];
+        let child = processor.create_task(request).await.unwrap();
+        assert_eq!(child.status, TaskStatus::Blocked);
+        assert_eq!(processor.get_stats().blocked, 1);
+
+        let child = processor.wait_for_terminal(child.id, WAIT).await.unwrap();
+        let parent = processor.get_task(parent.id).await.unwrap();
+        assert_eq!(child.status, TaskStatus::Completed);
+        assert!(child.started_at.unwrap() >= parent.completed_at.unwrap());
+        assert_eq!(processor.get_stats().blocked, 0);
+
+        let mut request = CreateTaskRequest::new("Órfã", 10, TaskPriority::High);
+        request.depends_on = vec![Uuid::new_v4()];
+        assert!(processor.create_task(request).await.is_err());
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_failure_propagates_downstream() {
+        let processor = simulate_processor();
+
+        let mut request = CreateTaskRequest::new("Pai", 0, TaskPriority::Low);
+        request.kind = "instavel".to_string();
+        let parent = processor.create_task(request).await.unwrap();
+        let mut request = CreateTaskRequest::new("Filha", 10, TaskPriority::High);
+        request.depends_on = vec![parent.id];
+        let child = processor.create_task(request).await.unwrap();
+        let mut request = CreateTaskRequest::new("Neta", 10, TaskPriority::High);
+        request.depends_on = vec![child.id];
+        let grandchild = processor.create_task(request).await.unwrap();
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        assert_eq!(
+            processor
+                .wait_for_terminal(parent.id, WAIT)
+                .await
+                .unwrap()
+                .status,
+            TaskStatus::Failed
+        );
+        for id in [child.id, grandchild.id] {
+            let task = processor.wait_for_terminal(id, WAIT).await.unwrap();
+            assert_eq!(task.status, TaskStatus::Cancelled);
+            assert!(task.error_message.unwrap().contains("Dependência"));
+        }
+        let stats = processor.get_stats();
+        assert_eq!(stats.blocked, 0);
+        assert_eq!(stats.cancelled, 2);
+
+        let mut request = CreateTaskRequest::new("Tardia", 10, TaskPriority::High);
+        request.depends_on = vec![parent.id];
+        assert!(processor.create_task(request).await.is_err());
+        handle.abort();
+    }
+
+    fn workflow(edges: &[(&str, &[&str])]) -> CreateWorkflowRequest {
+        let tasks = edges
+            .iter()
+            .map(|(key, parents)| WorkflowTaskRequest {
+                key: key.to_string(),
+                depends_on: parents.iter().map(|p| p.to_string()).collect(),
+                task: CreateTaskRequest::new(*key, 10, TaskPriority::Medium),
+            })
+            .collect();
+        CreateWorkflowRequest { tasks }
+    }
+
+    #[tokio::test]
+    async fn test_workflow_runs_dag_and_rejects_cycles() {
+        let processor = simulate_processor();
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let cyclic = workflow(&[("a", &[]), ("b", &["a", "d"]), ("c", &["b"]), ("d", &["c"])]);
+        let err = processor
+            .create_workflow(DEFAULT_TENANT, cyclic)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Ciclo"), "{}", err);
+        assert!(processor
+            .list_tasks(&TaskQuery::default())
+            .await
+            .tasks
+            .is_empty());
+
+        // Losango: a -> (b, c) -> d
+        let diamond = workflow(&[("d", &["b", "c"]), ("b", &["a"]), ("c", &["a"]), ("a", &[])]);
+        let created = processor.create_workflow(DEFAULT_TENANT, diamond).unwrap();
+        assert_eq!(created.status, WorkflowStatus::Pending);
+        let keys: Vec<&str> = created.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(keys, vec!["d", "b", "c", "a"]);
+        assert_eq!(created.tasks[0].depends_on.len(), 2);
+
+        processor
+            .wait_for_terminal(created.tasks[0].id, WAIT)
+            .await
+            .unwrap();
+        let done = processor.get_workflow(created.id).unwrap();
+        assert_eq!(done.status, WorkflowStatus::Completed);
+        let finished = |name: &str| {
+            done.tasks
+                .iter()
+                .find(|t| t.name == name)
+                .and_then(|t| t.completed_at)
+                .unwrap()
+        };
+        assert!(finished("a") <= finished("b"));
+        assert!(finished("c") <= finished("d"));
+        assert!(processor.get_workflow(Uuid::new_v4()).is_none());
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_failed_task_is_retried_until_success() {
+        let mut registry = HandlerRegistry::new();
+        registry.register("instavel", FlakyHandler::new(2));
+        let processor = Arc::new(TaskProcessor::with_registry(registry));
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let mut request = CreateTaskRequest::new("Instável", 0, TaskPriority::High);
+        request.kind = "instavel".to_string();
+        request.retry_policy = Some(RetryPolicy {
+            max_attempts: 3,
+            backoff: Backoff::Exponential {
+                initial_ms: 10,
+                multiplier: 2.0,
+                max_ms: 1000,
+            },
+            jitter: false,
+        });
+        let task = processor.create_task(request).await.unwrap();
+        let task = processor.wait_for_terminal(task.id, WAIT).await.unwrap();
+        handle.abort();
+
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert_eq!(task.attempt, 3);
+        assert_eq!(task.attempts.len(), 3);
+        assert!(task.attempts[0].error.is_some());
+        assert!(task.attempts[2].error.is_none());
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.retries, 2);
+        assert_eq!(task.retries, 2);
+        // Um reinício reconstrói o total a partir das tarefas
+        let restored = Stats::new();
+        restored.add(&task);
+        assert_eq!(restored.get_stats().retries, 2);
+        assert_eq!(stats.retrying, 0);
+        assert_eq!(stats.failed, 0);
+        assert_eq!(stats.completed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_exhausted_marks_failed() {
+        let mut registry = HandlerRegistry::new();
+        registry.register("instavel", FlakyHandler::new(5));
+        let processor = Arc::new(TaskProcessor::with_registry(registry));
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let mut request = CreateTaskRequest::new("Instável", 0, TaskPriority::High);
+        request.kind = "instavel".to_string();
+        request.retry_policy = Some(RetryPolicy {
+            max_attempts: 2,
+            backoff: Backoff::Fixed { delay_ms: 10 },
+            jitter: true,
+        });
+        let task = processor.create_task(request).await.unwrap();
+        let task = processor.wait_for_terminal(task.id, WAIT).await.unwrap();
+        handle.abort();
+
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert_eq!(task.attempts.len(), 2);
+
+        let stats = processor.get_stats();
+        assert_eq!(stats.retries, 1);
+        assert_eq!(stats.failed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_requeue_and_purge() {
+        let mut registry = HandlerRegistry::new();
+        registry.register("instavel", FlakyHandler::new(2));
+        let processor = Arc::new(TaskProcessor::with_registry(registry));
+
+        let worker = processor.clone();
+        let handle = tokio::spawn(async move { worker.start_processing().await });
+
+        let mut ids = Vec::new();
+        for name in ["A", "B"] {
+            let mut request = CreateTaskRequest::new(name, 0, TaskPriority::High);
+            request.kind = "instavel".to_string();
+            ids.push(processor.create_task(request).await.unwrap().id);
+        }
+        wait_until(&processor, || processor.list_dead_letters().len() == 2).await;
+
+        // Ambas falharam e saíram da coleção principal
+        assert!(processor
+            .list_tasks(&TaskQuery::default())
+            .await
+            .tasks
+            .is_empty());
+        assert_eq!(processor.list_dead_letters().len(), 2);
+        let failed = processor.get_task(ids[0]).await.unwrap();
+        assert_eq!(failed.status, TaskStatus::Failed);
+        assert_eq!(failed.attempts.len(), 1);
+        assert_eq!(processor.get_stats().failed, 2);
+
+        // O handler já esgotou as falhas: a tarefa devolvida completa
+        let requeued = processor.requeue_dead_letter(ids[0]).unwrap();
+        assert_eq!(requeued.status, TaskStatus::Pending);
+        let task = processor.wait_for_terminal(ids[0], WAIT).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert_eq!(task.attempts.len(), 2);
+        assert_eq!(processor.get_stats().failed, 1);
+
+        processor.purge_dead_letter(ids[1]).unwrap();
+        assert!(processor.list_dead_letters().is_empty());
+        assert!(processor.get_task(ids[1]).await.is_none());
+        assert!(processor.purge_dead_letter(ids[1]).is_err());
+        handle.abort();
+    }
+
+    // Falha nas primeiras `failures` execuções
+    struct FlakyHandler {
+        remaining: Arc<std::sync::atomic::AtomicU32>,
+    }
+
+    impl FlakyHandler {
+        fn new(failures: u32) -> Self {
+            Self {
+                remaining: Arc::new(std::sync::atomic::AtomicU32::new(failures)),
+            }
+        }
+    }
+
+    impl TaskHandler for FlakyHandler {
+        fn run(&self, _ctx: TaskContext) -> BoxFuture<'static, HandlerResult> {
+            let remaining = self.remaining.clone();
+            Box::pin(async move {
+                let left = remaining.load(std::sync::atomic::Ordering::SeqCst);
+                if left > 0 {
+                    remaining.store(left - 1, std::sync::atomic::Ordering::SeqCst);
+                    return Err("falha temporária".to_string());
+                }
+                Ok(serde_json::Value::Null)
+            })
+        }
+    }
+
+    const STATUSES: [TaskStatus; 8] = [
+        TaskStatus::Blocked,
+        TaskStatus::Scheduled,
+        TaskStatus::Pending,
+        TaskStatus::Processing,
+        TaskStatus::Retrying,
+        TaskStatus::Completed,
+        TaskStatus::Failed,
+        TaskStatus::Cancelled,
+    ];
+
+    // Operações sobre a n-ésima tarefa criada (índice tomado em módulo)
+    #[derive(Debug, Clone)]
+    enum Op {
+        Create {
+            delayed: bool,
+            parent: Option<usize>,
+            retries: bool,
+            other_tenant: bool,
+        },
+        Cancel(usize),
+        Start(usize),
+        Finish(usize, u8),
+        // Execuções que falham até esgotar as tentativas: leva a tarefa à DLQ
+        Fail(usize),
+        Promote(usize),
+        Requeue(usize),
+        Delete(usize),
+        Force(usize, usize),
+    }
+
+    fn op() -> impl proptest::strategy::Strategy<Value = Op> {
+        use proptest::prelude::*;
+        prop_oneof![
+            (
+                any::<bool>(),
+                proptest::option::of(0..16usize),
+                any::<bool>(),
+                any::<bool>()
+            )
+                .prop_map(|(delayed, parent, retries, other_tenant)| Op::Create {
+                    delayed,
+                    parent,
+                    retries,
+                    other_tenant
+                }),
+            (0..16usize).prop_map(Op::Cancel),
+            (0..16usize).prop_map(Op::Start),
+            (0..16usize, 0..3u8).prop_map(|(i, kind)| Op::Finish(i, kind)),
+            (0..16usize).prop_map(Op::Fail),
+            (0..16usize).prop_map(Op::Promote),
+            (0..16usize).prop_map(Op::Requeue),
+            (0..16usize).prop_map(Op::Delete),
+            (0..16usize, 0..STATUSES.len()).prop_map(|(i, s)| Op::Force(i, s)),
+        ]
+    }
+
+    // Prefere uma tarefa da DLQ, para a devolução de fato ser exercitada
+    fn requeue_target(processor: &TaskProcessor, ids: &[Uuid], i: usize) -> Option<Uuid> {
+        let mut dead: Vec<Uuid> = processor.dead_letters.list().iter().map(|t| t.id).collect();
+        dead.sort();
+        dead.get(i % dead.len().max(1))
+            .or_else(|| ids.get(i % ids.len().max(1)))
+            .copied()
+    }
+
+    fn status_of(processor: &TaskProcessor, id: Uuid) -> Option<TaskStatus> {
+        processor.find_task(id).map(|t| t.status)
+    }
+
+    async fn apply_op(processor: &Arc<TaskProcessor>, ids: &mut Vec<Uuid>, op: Op) {
+        let pick = |i: usize| ids.get(i % ids.len().max(1)).copied();
+        match op {
+            Op::Create {
+                delayed,
+                parent,
+                retries,
+                other_tenant,
+            } => {
+                let mut request = CreateTaskRequest::new("prop", 10, TaskPriority::Medium);
+                if other_tenant {
+                    request.tenant = OTHER_TENANT.to_string();
+                }
+                if delayed {
+                    request.run_at = Some(chrono::Utc::now() + chrono::Duration::hours(1));
+                }
+                if let Some(parent) = parent.and_then(pick) {
+                    request.depends_on = vec![parent];
+                }
+                if retries {
+                    request.retry_policy = Some(RetryPolicy {
+                        max_attempts: 2,
+                        backoff: Backoff::Fixed { delay_ms: 60_000 },
+                        jitter: false,
+                    });
+                }
+                if let Ok(task) = processor.create_task(request).await {
+                    ids.push(task.id);
+                }
+            }
+            Op::Cancel(i) => {
+                if let Some(id) = pick(i) {
+                    let _ = processor.cancel_task(id).await;
+                }
+            }
+            Op::Start(i) => {
+                if let Some(id) = pick(i) {
+                    processor.start(id);
+                }
+            }
+            Op::Finish(i, kind) => {
+                if let Some(id) = pick(i) {
+                    let outcome = match kind {
+                        0 => Ok(Ok(StoredOutput::Inline(serde_json::Value::Null))),
+                        1 => Ok(Err("falha".to_string())),
+                        _ => Err(50),
+                    };
+                    processor.finish(id, outcome);
+                }
+            }
+            Op::Fail(i) => {
+                if let Some(id) = pick(i) {
+                    while processor.start(id).is_some() {
+                        processor.finish(id, Ok(Err("falha".to_string())));
+                        if status_of(processor, id) == Some(TaskStatus::Retrying) {
+                            processor.promote_due(id);
+                        }
+                    }
+                }
+            }
+            Op::Promote(i) => {
+                if let Some(id) = pick(i) {
+                    processor.promote_due(id);
+                }
+            }
+            Op::Requeue(i) => {
+                if let Some(id) = requeue_target(processor, ids, i) {
+                    let dead = processor.dead_letters.get(id);
+                    let result = processor.requeue_dead_letter(id);
+                    assert_eq!(result.is_ok(), dead.is_some());
+                    if dead.is_some() {
+                        assert!(processor.dead_letters.get(id).is_none());
+                        assert_eq!(status_of(processor, id), Some(TaskStatus::Pending));
+                    }
+                }
+            }
+            Op::Delete(i) => {
+                if let Some(id) = pick(i) {
+                    let _ = processor.delete_task(id);
+                }
+            }
+            Op::Force(i, status) => {
+                let Some(id) = pick(i) else {
+                    return;
+                };
+                let Some(before) = processor.store.get(id) else {
+                    return;
+                };
+                let next = STATUSES[status].clone();
+                let result = processor.transition(id, |t| {
+                    t.status = next.clone();
+                    true
+                });
+                let after = processor.store.get(id).unwrap();
+                if before.status.can_transition_to(&next) {
+                    assert!(result.is_ok());
+                    assert_eq!(after.status, next);
+                } else {
+                    assert!(result.is_err());
+                    assert_eq!(after.status, before.status);
+                }
+            }
+        }
+    }
+
+    // Os contadores batem com as tarefas armazenadas e com o que um reinício reconstruiria
+    fn assert_counters_consistent(processor: &TaskProcessor) {
+        let tasks: Vec<Task> = processor
+            .store
+            .list()
+            .into_iter()
+            .chain(processor.dead_letters.list())
+            .collect();
+        let stats = processor.stats.get_stats();
+        let count = |status: TaskStatus| tasks.iter().filter(|t| t.status == status).count() as u64;
+
+        assert_eq!(stats.total_tasks, tasks.len() as u64);
+        assert_eq!(stats.blocked, count(TaskStatus::Blocked));
+        assert_eq!(stats.scheduled, count(TaskStatus::Scheduled));
+        assert_eq!(stats.pending, count(TaskStatus::Pending));
+        assert_eq!(stats.processing, count(TaskStatus::Processing));
+        assert_eq!(stats.retrying, count(TaskStatus::Retrying));
+        assert_eq!(stats.completed, count(TaskStatus::Completed));
+        assert_eq!(stats.failed, count(TaskStatus::Failed));
+        assert_eq!(stats.cancelled, count(TaskStatus::Cancelled));
+        let retries: u32 = tasks.iter().map(|t| t.retries).sum();
+        assert_eq!(stats.retries, retries as u64);
+
+        let rebuilt = Stats::new();
+        for task in &tasks {
+            rebuilt.add(task);
+        }
+        // Os contadores de cada tenant também batem com a reconstrução
+        for tenant in [DEFAULT_TENANT, OTHER_TENANT] {
+            let live = processor.stats.tenant_stats(tenant, None);
+            let mut expected = rebuilt.tenant_stats(tenant, None);
+            expected.latency = live.latency.clone();
+            assert_eq!(
+                serde_json::to_value(&live).unwrap(),
+                serde_json::to_value(&expected).unwrap()
+            );
+        }
+
+        let mut rebuilt = rebuilt.get_stats();
+        rebuilt.latency = stats.latency.clone();
+        assert_eq!(
+            serde_json::to_value(&stats).unwrap(),
+            serde_json::to_value(&rebuilt).unwrap()
+        );
+    }
+
+    const OTHER_TENANT: &str = "outro";
+
+    proptest::proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(64))]
+
+        #[test]
+        fn test_counters_match_tasks_after_random_operations(
+            ops in proptest::collection::vec(op(), 1..60)
+        ) {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                let processor = Arc::new(TaskProcessor::new());
+                let mut ids = Vec::new();
+                for op in ops {
+                    apply_op(&processor, &mut ids, op).await;
+                    assert_counters_consistent(&processor);
+                }
+            });
+        }
+    }
+
+    struct PayloadHandler;
+
+    impl TaskHandler for PayloadHandler {
+        fn run(&self, ctx: TaskContext) -> BoxFuture<'static, HandlerResult> {
+            Box::pin(async move { Ok(ctx.task.payload) })
+        }
+    }
+
+    // Termina com um resultado grande só depois de cancelada
+    struct LateOutputHandler;
+
+    impl TaskHandler for LateOutputHandler {
+        fn run(&self, ctx: TaskContext) -> BoxFuture<'static, HandlerResult> {
+            Box::pin(async move {
+                ctx.cancelled().await;
+                Ok(serde_json::json!({ "data": "x".repeat(64) }))
+            })
+        }
     }
 }
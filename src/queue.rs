@@ -0,0 +1,283 @@
+use chrono::{DateTime, Duration, Utc};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::models::{Task, TaskPriority};
+
+type EntryKey = (DateTime<Utc>, u64);
+
+// Entrada da fila de prontas; guarda o necessário para escolher a próxima tarefa
+#[derive(Debug, Clone)]
+pub struct QueueEntry {
+    pub id: Uuid,
+    pub kind: String,
+    pub tenant: String,
+    pub priority: TaskPriority,
+    pub enqueued_at: DateTime<Utc>,
+}
+
+impl QueueEntry {
+    pub fn from_task(task: &Task) -> Self {
+        Self {
+            id: task.id,
+            kind: task.kind.clone(),
+            tenant: task.tenant.clone(),
+            priority: task.priority.clone(),
+            enqueued_at: Utc::now(),
+        }
+    }
+}
+
+// Motivo pelo qual `accept` recusou uma entrada em `pop_first`: as demais
+// entradas do mesmo tipo ou tenant são puladas na mesma passada, e `All`
+// encerra a passada (ex.: pool sem nenhuma vaga)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    Kind,
+    Tenant,
+    All,
+}
+
+// Fila de prontas ordenada por prioridade e, dentro da mesma prioridade, por `enqueued_at`.
+// Com `aging` configurado, cada intervalo de espera sobe a entrada um nível de prioridade;
+// como a espera também conta de `enqueued_at`, a cabeça de cada classe é sempre a que
+// mais subiu (uma tarefa devolvida à fila vai para o fim, mesmo criada há muito tempo).
+#[derive(Debug, Default)]
+pub struct ReadyQueue {
+    // Índice 0 = Low, 1 = Medium, 2 = High
+    classes: [BTreeMap<EntryKey, QueueEntry>; 3],
+    index: HashMap<Uuid, (usize, EntryKey)>,
+    aging: Option<Duration>,
+    seq: u64,
+}
+
+fn rank(priority: &TaskPriority) -> usize {
+    match priority {
+        TaskPriority::Low => 0,
+        TaskPriority::Medium => 1,
+        TaskPriority::High => 2,
+    }
+}
+
+impl ReadyQueue {
+    pub fn new(aging: Option<Duration>) -> Self {
+        Self {
+            aging: aging.filter(|aging| *aging > Duration::zero()),
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, entry: QueueEntry) {
+        if self.index.contains_key(&entry.id) {
+            return;
+        }
+
+        self.seq += 1;
+        let class = rank(&entry.priority);
+        let key = (entry.enqueued_at, self.seq);
+        self.index.insert(entry.id, (class, key));
+        self.classes[class].insert(key, entry);
+    }
+
+    pub fn remove(&mut self, id: Uuid) -> Option<QueueEntry> {
+        let (class, key) = self.index.remove(&id)?;
+        self.classes[class].remove(&key)
+    }
+
+    fn effective_rank(&self, entry: &QueueEntry, now: DateTime<Utc>) -> usize {
+        let base = rank(&entry.priority);
+        match self.aging {
+            Some(aging) => {
+                let waited = (now - entry.enqueued_at).num_milliseconds().max(0);
+                let levels = (waited / aging.num_milliseconds()) as usize;
+                (base + levels).min(2)
+            }
+            None => base,
+        }
+    }
+
+    // Remove a melhor entrada aceita por `accept` (ex.: tipo com vaga livre no pool)
+    pub fn pop_first<T>(
+        &mut self,
+        mut accept: impl FnMut(&QueueEntry) -> Result<T, Rejection>,
+    ) -> Option<(QueueEntry, T)> {
+        let now = Utc::now();
+
+        // Intercala as três classes, escolhendo sempre a cabeça de maior prioridade efetiva
+        let found = {
+            // Tipos e tenants já recusados nesta passada
+            let mut kinds: HashSet<&str> = HashSet::new();
+            let mut tenants: HashSet<&str> = HashSet::new();
+            let mut iters: Vec<_> = self
+                .classes
+                .iter()
+                .map(|class| class.iter().peekable())
+                .collect();
+
+            loop {
+                let best = iters
+                    .iter_mut()
+                    .enumerate()
+                    .filter_map(|(class, iter)| {
+                        let (key, entry) = iter.peek()?;
+                        Some((
+                            Reverse(self.effective_rank(entry, now)),
+                            key.0,
+                            key.1,
+                            class,
+                        ))
+                    })
+                    .min();
+
+                let (_, _, _, class) = best?;
+                let (key, entry) = iters[class].next()?;
+                if kinds.contains(entry.kind.as_str()) || tenants.contains(entry.tenant.as_str()) {
+                    continue;
+                }
+                match accept(entry) {
+                    Ok(value) => break (class, *key, value),
+                    Err(Rejection::Kind) => {
+                        kinds.insert(&entry.kind);
+                    }
+                    Err(Rejection::Tenant) => {
+                        tenants.insert(&entry.tenant);
+                    }
+                    Err(Rejection::All) => return None,
+                }
+            }
+        };
+
+        let (class, key, value) = found;
+        let entry = self.classes[class].remove(&key)?;
+        self.index.remove(&entry.id);
+        Some((entry, value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: &str, priority: TaskPriority, age_ms: i64) -> QueueEntry {
+        QueueEntry {
+            id: Uuid::new_v4(),
+            kind: kind.to_string(),
+            tenant: crate::models::DEFAULT_TENANT.to_string(),
+            priority,
+            enqueued_at: Utc::now() - Duration::milliseconds(age_ms),
+        }
+    }
+
+    fn pop_all(queue: &mut ReadyQueue) -> Vec<Uuid> {
+        std::iter::from_fn(|| queue.pop_first(|_| Ok(())).map(|(e, _)| e.id)).collect()
+    }
+
+    #[test]
+    fn test_priority_then_fifo() {
+        let mut queue = ReadyQueue::new(None);
+        let low = entry("simulate", TaskPriority::Low, 300);
+        let high_new = entry("simulate", TaskPriority::High, 10);
+        let high_old = entry("simulate", TaskPriority::High, 200);
+        let medium = entry("simulate", TaskPriority::Medium, 100);
+        for e in [&low, &high_new, &high_old, &medium] {
+            queue.push(e.clone());
+        }
+
+        assert_eq!(
+            pop_all(&mut queue),
+            vec![high_old.id, high_new.id, medium.id, low.id]
+        );
+    }
+
+    #[test]
+    fn test_pop_first_skips_rejected_entries() {
+        let mut queue = ReadyQueue::new(None);
+        let slow = entry("lento", TaskPriority::High, 0);
+        let fast = entry("rapido", TaskPriority::Low, 0);
+        queue.push(slow.clone());
+        queue.push(fast.clone());
+
+        let (popped, _) = queue
+            .pop_first(|e| match e.kind.as_str() {
+                "rapido" => Ok(()),
+                _ => Err(Rejection::Kind),
+            })
+            .unwrap();
+        assert_eq!(popped.id, fast.id);
+        assert_eq!(queue.len(), 1);
+
+        assert!(queue.remove(slow.id).is_some());
+        assert!(queue.pop_first(|_| Ok(())).is_none());
+    }
+
+    #[test]
+    fn test_pop_first_asks_once_per_rejected_kind_or_tenant() {
+        let mut queue = ReadyQueue::new(None);
+        for i in 0..50 {
+            let mut e = entry(
+                if i % 2 == 0 { "lento" } else { "simulate" },
+                TaskPriority::High,
+                0,
+            );
+            e.tenant = if i % 5 == 0 { "cheio" } else { "livre" }.to_string();
+            queue.push(e);
+        }
+        let last = entry("rapido", TaskPriority::Low, 0);
+        queue.push(last.clone());
+
+        let mut calls = 0;
+        let (popped, _) = queue
+            .pop_first(|e| {
+                calls += 1;
+                match (e.tenant.as_str(), e.kind.as_str()) {
+                    ("cheio", _) => Err(Rejection::Tenant),
+                    (_, "rapido") => Ok(()),
+                    _ => Err(Rejection::Kind),
+                }
+            })
+            .unwrap();
+        assert_eq!(popped.id, last.id);
+        // Uma recusa por tenant e uma por tipo, além da entrada aceita
+        assert!(calls <= 4, "accept chamado {} vezes", calls);
+
+        calls = 0;
+        assert!(queue
+            .pop_first(|_| -> Result<(), _> {
+                calls += 1;
+                Err(Rejection::All)
+            })
+            .is_none());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_aging_promotes_old_low_tasks() {
+        let mut queue = ReadyQueue::new(Some(Duration::milliseconds(100)));
+        let high = entry("simulate", TaskPriority::High, 0);
+        // Esperou dois intervalos: Low -> High, e é mais antiga
+        let old_low = entry("simulate", TaskPriority::Low, 250);
+        queue.push(high.clone());
+        queue.push(old_low.clone());
+
+        assert_eq!(pop_all(&mut queue), vec![old_low.id, high.id]);
+    }
+
+    #[test]
+    fn test_requeued_task_does_not_hold_back_aged_entries() {
+        let mut queue = ReadyQueue::new(Some(Duration::milliseconds(100)));
+        let high = entry("simulate", TaskPriority::High, 0);
+        let aged_low = entry("simulate", TaskPriority::Low, 250);
+        // Criada antes das demais, mas acabou de voltar à fila (ex.: nova tentativa)
+        let requeued = entry("simulate", TaskPriority::Low, 0);
+        for e in [&requeued, &high, &aged_low] {
+            queue.push(e.clone());
+        }
+
+        assert_eq!(pop_all(&mut queue), vec![aged_low.id, high.id, requeued.id]);
+    }
+}
@@ -0,0 +1,319 @@
+use futures_util::future::BoxFuture;
+use jsonschema::JSONSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::logs::{LogLevel, TaskLogger};
+use crate::models::Task;
+
+// Tipo usado quando a requisição não informa `kind`
+pub const DEFAULT_KIND: &str = "simulate";
+
+pub type HandlerResult = Result<serde_json::Value, String>;
+
+// Contexto entregue ao handler durante a execução de uma tarefa
+#[derive(Debug, Clone)]
+pub struct TaskContext {
+    pub task: Task,
+    cancel: CancellationToken,
+    logger: Option<TaskLogger>,
+}
+
+impl TaskContext {
+    pub fn new(task: Task, cancel: CancellationToken) -> Self {
+        Self {
+            task,
+            cancel,
+            logger: None,
+        }
+    }
+
+    pub fn with_logger(mut self, logger: TaskLogger) -> Self {
+        self.logger = Some(logger);
+        self
+    }
+
+    // Escreve no log da tarefa (`GET /api/tasks/:id/logs`) e no log do servidor
+    pub fn log(&self, level: LogLevel, message: impl Into<String>) {
+        let message = message.into();
+        let id = self.task.id;
+        match level {
+            LogLevel::Debug => debug!(task.id = %id, "{}", message),
+            LogLevel::Info => info!(task.id = %id, "{}", message),
+            LogLevel::Warn => warn!(task.id = %id, "{}", message),
+            LogLevel::Error => error!(task.id = %id, "{}", message),
+        }
+        if let Some(logger) = &self.logger {
+            logger.log(level, message);
+        }
+    }
+
+    // Completa quando o cancelamento é solicitado; o handler pode então fazer limpeza
+    // e retornar. Se não retornar dentro do prazo de tolerância, a execução é abortada.
+    pub async fn cancelled(&self) {
+        self.cancel.cancelled().await
+    }
+}
+
+pub trait TaskHandler: Send + Sync + 'static {
+    fn run(&self, ctx: TaskContext) -> BoxFuture<'static, HandlerResult>;
+}
+
+// Adaptador que desserializa o payload em `P` e serializa o retorno `R`
+pub struct FnHandler<P, R, F> {
+    func: F,
+    _marker: PhantomData<fn(P) -> R>,
+}
+
+impl<P, R, F, Fut> TaskHandler for FnHandler<P, R, F>
+where
+    P: DeserializeOwned + Send + 'static,
+    R: Serialize + Send + 'static,
+    F: Fn(P, TaskContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<R, String>> + Send + 'static,
+{
+    fn run(&self, ctx: TaskContext) -> BoxFuture<'static, HandlerResult> {
+        let payload = match serde_json::from_value::<P>(ctx.task.payload.clone()) {
+            Ok(payload) => payload,
+            Err(e) => {
+                let error = format!("Payload inválido: {}", e);
+                return Box::pin(async move { Err(error) });
+            }
+        };
+
+        let fut = (self.func)(payload, ctx);
+        Box::pin(async move {
+            let result = fut.await?;
+            serde_json::to_value(result).map_err(|e| format!("Resultado inválido: {}", e))
+        })
+    }
+}
+
+// Comportamento original do servidor: dorme `duration_ms` e falha ocasionalmente
+pub struct SimulateHandler {
+    failure_rate: f64,
+}
+
+impl SimulateHandler {
+    pub fn new(failure_rate: f64) -> Self {
+        Self { failure_rate }
+    }
+}
+
+impl Default for SimulateHandler {
+    fn default() -> Self {
+        // Simular falha ocasional (5% de chance)
+        Self::new(0.05)
+    }
+}
+
+impl TaskHandler for SimulateHandler {
+    fn run(&self, ctx: TaskContext) -> BoxFuture<'static, HandlerResult> {
+        let failure_rate = self.failure_rate;
+        Box::pin(async move {
+            let duration_ms = ctx.task.duration_ms;
+            ctx.log(
+                LogLevel::Info,
+                format!("Simulando processamento por {} ms", duration_ms),
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_millis(duration_ms)) => {}
+                _ = ctx.cancelled() => return Err("Processamento interrompido".to_string()),
+            }
+
+            if rand::random::<f64>() < failure_rate {
+                return Err("Erro aleatório durante processamento".to_string());
+            }
+
+            Ok(serde_json::json!({ "slept_ms": duration_ms }))
+        })
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct HandlerRegistry {
+    handlers: HashMap<String, Arc<dyn TaskHandler>>,
+    // JSON Schema opcional que o `payload` de cada tipo precisa satisfazer
+    schemas: HashMap<String, Arc<JSONSchema>>,
+}
+
+impl HandlerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Registro com os handlers embutidos ("simulate" e "echo") já disponíveis
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(DEFAULT_KIND, SimulateHandler::default());
+        registry.register_fn("echo", |payload: serde_json::Value, _ctx| async move {
+            Ok(payload)
+        });
+        registry
+    }
+
+    pub fn register<H: TaskHandler>(&mut self, kind: impl Into<String>, handler: H) {
+        self.handlers.insert(kind.into(), Arc::new(handler));
+    }
+
+    pub fn register_fn<P, R, F, Fut>(&mut self, kind: impl Into<String>, func: F)
+    where
+        P: DeserializeOwned + Send + 'static,
+        R: Serialize + Send + 'static,
+        F: Fn(P, TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, String>> + Send + 'static,
+    {
+        self.register(
+            kind,
+            FnHandler {
+                func,
+                _marker: PhantomData,
+            },
+        );
+    }
+
+    pub fn set_schema(&mut self, kind: &str, schema: &serde_json::Value) -> Result<(), String> {
+        if !self.contains(kind) {
+            return Err(format!("Tipo de tarefa desconhecido: {}", kind));
+        }
+        let compiled = JSONSchema::compile(schema)
+            .map_err(|e| format!("Schema inválido para '{}': {}", kind, e))?;
+        self.schemas.insert(kind.to_string(), Arc::new(compiled));
+        Ok(())
+    }
+
+    // Carrega `<tipo>.json` de `dir` como schema do payload de cada tipo
+    pub fn load_schemas(&mut self, dir: &Path) -> Result<Vec<String>, String> {
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| format!("Falha ao ler diretório de schemas: {}", e))?;
+
+        let mut loaded = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(kind) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let schema = std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string()))
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            self.set_schema(kind, &schema)?;
+            loaded.push(kind.to_string());
+        }
+        loaded.sort();
+        Ok(loaded)
+    }
+
+    pub fn validate_payload(&self, kind: &str, payload: &serde_json::Value) -> Result<(), String> {
+        let Some(schema) = self.schemas.get(kind) else {
+            return Ok(());
+        };
+        schema.validate(payload).map_err(|errors| {
+            let details: Vec<String> = errors
+                .map(|e| match e.instance_path.to_string() {
+                    path if path.is_empty() => e.to_string(),
+                    path => format!("{}: {}", path, e),
+                })
+                .collect();
+            format!("Payload inválido para '{}': {}", kind, details.join("; "))
+        })
+    }
+
+    pub fn get(&self, kind: &str) -> Option<Arc<dyn TaskHandler>> {
+        self.handlers.get(kind).cloned()
+    }
+
+    pub fn contains(&self, kind: &str) -> bool {
+        self.handlers.contains_key(kind)
+    }
+
+    pub fn kinds(&self) -> Vec<String> {
+        let mut kinds: Vec<String> = self.handlers.keys().cloned().collect();
+        kinds.sort();
+        kinds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateTaskRequest, TaskPriority};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Sum {
+        a: i64,
+        b: i64,
+    }
+
+    fn task_with_payload(payload: serde_json::Value) -> Task {
+        let mut request = CreateTaskRequest::new("Soma", 0, TaskPriority::Medium);
+        request.kind = "sum".to_string();
+        request.payload = payload;
+        Task::from_request(request)
+    }
+
+    #[tokio::test]
+    async fn test_typed_handler() {
+        let mut registry = HandlerRegistry::new();
+        registry.register_fn("sum", |payload: Sum, _ctx| async move {
+            Ok(payload.a + payload.b)
+        });
+
+        let handler = registry.get("sum").unwrap();
+        let task = task_with_payload(serde_json::json!({ "a": 2, "b": 3 }));
+        let result = handler
+            .run(TaskContext::new(task, CancellationToken::new()))
+            .await;
+        assert_eq!(result, Ok(serde_json::json!(5)));
+
+        let task = task_with_payload(serde_json::json!({ "a": "x" }));
+        let result = handler
+            .run(TaskContext::new(task, CancellationToken::new()))
+            .await;
+        assert!(result.unwrap_err().starts_with("Payload inválido"));
+    }
+
+    #[test]
+    fn test_builtins() {
+        let registry = HandlerRegistry::with_builtins();
+        assert!(registry.contains(DEFAULT_KIND));
+        assert_eq!(
+            registry.kinds(),
+            vec!["echo".to_string(), DEFAULT_KIND.to_string()]
+        );
+    }
+
+    #[test]
+    fn test_payload_schema_validation() {
+        let mut registry = HandlerRegistry::with_builtins();
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["to"],
+            "properties": { "to": { "type": "string" } }
+        });
+        assert!(registry.set_schema("inexistente", &schema).is_err());
+        registry.set_schema("echo", &schema).unwrap();
+
+        assert!(registry
+            .validate_payload("echo", &serde_json::json!({ "to": "a@b.c" }))
+            .is_ok());
+        let err = registry
+            .validate_payload("echo", &serde_json::json!({ "to": 42 }))
+            .unwrap_err();
+        assert!(err.contains("/to"), "{}", err);
+        assert!(registry
+            .validate_payload("simulate", &serde_json::json!(42))
+            .is_ok());
+    }
+}
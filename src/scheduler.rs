@@ -0,0 +1,510 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast::error::RecvError, Notify};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::models::{
+    CreateScheduleRequest, CreateTaskRequest, OverlapPolicy, Schedule, ScheduleRun,
+    ScheduleRunOutcome,
+};
+use crate::processor::TaskProcessor;
+use crate::store::StoreError;
+use crate::timer::TimerQueue;
+
+// Limite de horários retornados pela prévia de próximas execuções
+pub const MAX_PREVIEW: usize = 100;
+
+// Agendamentos recorrentes: a cada disparo cria uma tarefa via `TaskProcessor::create_task`.
+// Com `path` definido, os agendamentos são gravados em um arquivo JSON a cada alteração.
+pub struct Scheduler {
+    processor: Arc<TaskProcessor>,
+    schedules: Mutex<HashMap<Uuid, Schedule>>,
+    path: Option<PathBuf>,
+    timers: Mutex<TimerQueue>,
+    wakeup: Notify,
+}
+
+impl Scheduler {
+    pub fn open(processor: Arc<TaskProcessor>, path: Option<&Path>) -> Result<Self, StoreError> {
+        let mut schedules = HashMap::new();
+        if let Some(path) = path {
+            match fs::read(path) {
+                Ok(bytes) => {
+                    let loaded: Vec<Schedule> = serde_json::from_slice(&bytes)?;
+                    schedules.extend(loaded.into_iter().map(|s| (s.id, s)));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        // Execuções perdidas enquanto o processo estava parado não são repetidas
+        let now = Utc::now();
+        let mut timers = TimerQueue::new();
+        for schedule in schedules.values_mut().filter(|s| !s.paused) {
+            if schedule.next_run_at.is_none_or(|at| at < now) {
+                schedule.next_run_at = schedule.trigger.next_after(now);
+            }
+            if let Some(at) = schedule.next_run_at {
+                timers.push(at, schedule.id);
+            }
+        }
+
+        Ok(Self {
+            processor,
+            schedules: Mutex::new(schedules),
+            path: path.map(Path::to_path_buf),
+            timers: Mutex::new(timers),
+            wakeup: Notify::new(),
+        })
+    }
+
+    fn validate(&self, request: &CreateScheduleRequest) -> Result<(), String> {
+        if request.name.is_empty() {
+            return Err("Nome do agendamento não pode estar vazio".to_string());
+        }
+        request.trigger.validate()?;
+        if request.task.run_at.is_some() || request.task.delay_ms.is_some() {
+            return Err("run_at e delay_ms não são permitidos em agendamentos".to_string());
+        }
+        // Cada disparo devolveria a primeira tarefa criada
+        if request.task.idempotency_key.is_some() {
+            return Err("idempotency_key não é permitida em agendamentos".to_string());
+        }
+        self.processor.validate_request(&request.task)
+    }
+
+    pub fn create(&self, request: CreateScheduleRequest) -> Result<Schedule, String> {
+        self.validate(&request)?;
+        let schedule = Schedule::from_request(request);
+
+        let mut schedules = self.schedules.lock().unwrap();
+        schedules.insert(schedule.id, schedule.clone());
+        self.persist(&schedules).map_err(|e| e.to_string())?;
+        drop(schedules);
+
+        self.arm(&schedule);
+        info!(
+            "📅 Agendamento criado: {} (ID: {})",
+            schedule.name, schedule.id
+        );
+        Ok(schedule)
+    }
+
+    pub fn list(&self) -> Vec<Schedule> {
+        let mut schedules: Vec<Schedule> =
+            self.schedules.lock().unwrap().values().cloned().collect();
+        schedules.sort_by_key(|s| s.created_at);
+        schedules
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<Schedule> {
+        self.schedules.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn update(
+        &self,
+        id: Uuid,
+        request: CreateScheduleRequest,
+    ) -> Result<Option<Schedule>, String> {
+        self.validate(&request)?;
+        let updated = self.modify(id, |schedule| schedule.apply(request))?;
+        if let Some(schedule) = &updated {
+            self.arm(schedule);
+        }
+        Ok(updated)
+    }
+
+    pub fn pause(&self, id: Uuid) -> Result<Option<Schedule>, String> {
+        self.modify(id, Schedule::pause)
+    }
+
+    pub fn resume(&self, id: Uuid) -> Result<Option<Schedule>, String> {
+        let resumed = self.modify(id, Schedule::resume)?;
+        if let Some(schedule) = &resumed {
+            self.arm(schedule);
+        }
+        Ok(resumed)
+    }
+
+    pub fn delete(&self, id: Uuid) -> Result<Option<Schedule>, String> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let removed = schedules.remove(&id);
+        if removed.is_some() {
+            self.persist(&schedules).map_err(|e| e.to_string())?;
+            info!("🗑️ Agendamento removido: {}", id);
+        }
+        Ok(removed)
+    }
+
+    // Próximos `count` horários de execução; vazio se o agendamento estiver pausado
+    pub fn preview(&self, id: Uuid, count: usize) -> Option<Vec<DateTime<Utc>>> {
+        let schedule = self.get(id)?;
+        let mut runs = Vec::new();
+        let mut next = schedule.next_run_at;
+        while let Some(at) = next.filter(|_| runs.len() < count.min(MAX_PREVIEW)) {
+            runs.push(at);
+            next = schedule.trigger.next_after(at);
+        }
+        Some(runs)
+    }
+
+    fn modify(
+        &self,
+        id: Uuid,
+        change: impl FnOnce(&mut Schedule),
+    ) -> Result<Option<Schedule>, String> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let Some(schedule) = schedules.get_mut(&id) else {
+            return Ok(None);
+        };
+        change(schedule);
+        let schedule = schedule.clone();
+        self.persist(&schedules).map_err(|e| e.to_string())?;
+        Ok(Some(schedule))
+    }
+
+    fn arm(&self, schedule: &Schedule) {
+        if let Some(at) = schedule.next_run_at {
+            self.timers.lock().unwrap().push(at, schedule.id);
+            self.wakeup.notify_one();
+        }
+    }
+
+    // Grava todos os agendamentos de uma vez (arquivo temporário + rename)
+    fn persist(&self, schedules: &HashMap<Uuid, Schedule>) -> Result<(), StoreError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let tmp = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp)?);
+            serde_json::to_writer(&mut writer, &schedules.values().collect::<Vec<_>>())?;
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+        }
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub async fn run(self: Arc<Self>) {
+        info!("📅 Iniciando agendador de tarefas recorrentes...");
+        let mut updates = self.processor.subscribe();
+
+        loop {
+            let due = self.timers.lock().unwrap().pop_due(Utc::now());
+            for id in due {
+                self.fire(id).await;
+            }
+
+            // Disparos acima já registraram seus próximos prazos
+            let next = self.timers.lock().unwrap().next_deadline();
+            let delay = next.map(|at| (at - Utc::now()).to_std().unwrap_or_default());
+            tokio::select! {
+                _ = tokio::time::sleep(delay.unwrap_or_default()), if delay.is_some() => {}
+                _ = self.wakeup.notified() => {}
+                update = updates.recv() => match update {
+                    // Fim de uma tarefa pode liberar uma execução guardada pela política `queue`
+                    Ok((_, task)) if task.status.is_terminal() => self.release_queued().await,
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(_)) => self.release_queued().await,
+                    Err(RecvError::Closed) => return,
+                },
+            }
+        }
+    }
+
+    async fn fire(&self, id: Uuid) {
+        let now = Utc::now();
+        let (template, overlap, active_task_id) = {
+            let mut schedules = self.schedules.lock().unwrap();
+            let Some(schedule) = schedules.get_mut(&id) else {
+                return;
+            };
+            // Entrada antiga do timer (agendamento pausado ou alterado)
+            let Some(at) = schedule.next_run_at.filter(|at| *at <= now) else {
+                return;
+            };
+            if schedule.paused {
+                return;
+            }
+
+            schedule.next_run_at = schedule
+                .trigger
+                .next_after(at)
+                .filter(|next| *next > now)
+                .or_else(|| schedule.trigger.next_after(now));
+            if let Some(next) = schedule.next_run_at {
+                self.timers.lock().unwrap().push(next, id);
+            }
+            (
+                schedule.task.clone(),
+                schedule.overlap,
+                schedule.active_task_id,
+            )
+        };
+
+        if !self.is_active(active_task_id).await {
+            return self.spawn(id, template).await;
+        }
+
+        let outcome = match overlap {
+            OverlapPolicy::Skip => ScheduleRunOutcome::Skipped,
+            OverlapPolicy::Queue => ScheduleRunOutcome::Queued,
+            OverlapPolicy::Allow => return self.spawn(id, template).await,
+        };
+        info!(
+            "⏭️ Agendamento {}: tarefa anterior ainda ativa ({:?})",
+            id, outcome
+        );
+        let queued = outcome == ScheduleRunOutcome::Queued;
+        let run = ScheduleRun {
+            at: now,
+            outcome,
+            task_id: None,
+            error: None,
+        };
+        self.record(id, run, |s| s.queued |= queued);
+    }
+
+    async fn spawn(&self, id: Uuid, template: CreateTaskRequest) {
+        let at = Utc::now();
+        let (run, task_id) = match self.processor.create_task(template).await {
+            Ok(task) => {
+                info!("📅 Agendamento {} criou a tarefa {}", id, task.id);
+                let run = ScheduleRun {
+                    at,
+                    outcome: ScheduleRunOutcome::Spawned,
+                    task_id: Some(task.id),
+                    error: None,
+                };
+                (run, Some(task.id))
+            }
+            Err(e) => {
+                warn!("⚠️ Agendamento {} não conseguiu criar tarefa: {}", id, e);
+                let run = ScheduleRun {
+                    at,
+                    outcome: ScheduleRunOutcome::Failed,
+                    task_id: None,
+                    error: Some(e),
+                };
+                (run, None)
+            }
+        };
+        self.record(id, run, |s| {
+            if task_id.is_some() {
+                s.active_task_id = task_id;
+            }
+        });
+    }
+
+    async fn release_queued(&self) {
+        let queued: Vec<(Uuid, CreateTaskRequest, Option<Uuid>)> = self
+            .schedules
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| s.queued && !s.paused)
+            .map(|s| (s.id, s.task.clone(), s.active_task_id))
+            .collect();
+
+        for (id, template, active_task_id) in queued {
+            if self.is_active(active_task_id).await {
+                continue;
+            }
+            if let Some(schedule) = self.schedules.lock().unwrap().get_mut(&id) {
+                schedule.queued = false;
+            }
+            self.spawn(id, template).await;
+        }
+    }
+
+    async fn is_active(&self, task_id: Option<Uuid>) -> bool {
+        match task_id {
+            Some(task_id) => self
+                .processor
+                .get_task(task_id)
+                .await
+                .is_some_and(|task| !task.status.is_terminal()),
+            None => false,
+        }
+    }
+
+    // Registra o resultado de uma execução (se o agendamento ainda existir) e persiste
+    fn record(&self, id: Uuid, run: ScheduleRun, change: impl FnOnce(&mut Schedule)) {
+        let mut schedules = self.schedules.lock().unwrap();
+        let Some(schedule) = schedules.get_mut(&id) else {
+            return;
+        };
+        change(schedule);
+        schedule.last_run = Some(run);
+        if let Err(e) = self.persist(&schedules) {
+            error!("❌ Falha ao persistir agendamento {}: {}", id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ScheduleTrigger, TaskPriority, TaskQuery, TaskStatus};
+    use crate::processor::tests::wait_until;
+    use crate::registry::{HandlerRegistry, SimulateHandler};
+
+    fn setup() -> (
+        Arc<TaskProcessor>,
+        Arc<Scheduler>,
+        Vec<tokio::task::JoinHandle<()>>,
+    ) {
+        let mut registry = HandlerRegistry::new();
+        registry.register("simulate", SimulateHandler::new(0.0));
+        let processor = Arc::new(TaskProcessor::with_registry(registry));
+        let scheduler = Arc::new(Scheduler::open(processor.clone(), None).unwrap());
+
+        let handles = vec![
+            tokio::spawn(processor.clone().start_processing()),
+            tokio::spawn(scheduler.clone().run()),
+        ];
+        (processor, scheduler, handles)
+    }
+
+    fn request(every_ms: u64, duration_ms: u64, overlap: OverlapPolicy) -> CreateScheduleRequest {
+        CreateScheduleRequest {
+            name: "Recorrente".to_string(),
+            trigger: ScheduleTrigger::Interval { every_ms },
+            overlap,
+            task: CreateTaskRequest::new("Tick", duration_ms, TaskPriority::Medium),
+            paused: false,
+        }
+    }
+
+    async fn count_named(processor: &TaskProcessor, name: &str) -> usize {
+        processor
+            .list_tasks(&TaskQuery::default())
+            .await
+            .tasks
+            .iter()
+            .filter(|task| task.name == name)
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_interval_spawns_tasks_until_paused() {
+        let (processor, scheduler, handles) = setup();
+        let schedule = scheduler
+            .create(request(20, 1, OverlapPolicy::Allow))
+            .unwrap();
+
+        wait_until(&processor, || processor.get_stats().total_tasks >= 3).await;
+        scheduler.pause(schedule.id).unwrap();
+
+        // Um segundo agendamento serve de relógio: os disparos rodam em sequência
+        // no mesmo laço, então um disparo em andamento já terminou quando o relógio dispara
+        let mut clock = request(20, 1, OverlapPolicy::Allow);
+        clock.task.name = "Relógio".to_string();
+        let clock = scheduler.create(clock).unwrap();
+        wait_until(&processor, || {
+            scheduler.get(clock.id).unwrap().last_run.is_some()
+        })
+        .await;
+        let spawned = count_named(&processor, "Tick").await;
+        assert!(spawned >= 3, "tarefas criadas: {}", spawned);
+
+        let paused = scheduler.get(schedule.id).unwrap();
+        assert_eq!(paused.next_run_at, None);
+        assert_eq!(
+            paused.last_run.unwrap().outcome,
+            ScheduleRunOutcome::Spawned
+        );
+        assert!(scheduler.preview(schedule.id, 3).unwrap().is_empty());
+
+        // Três disparos do relógio depois, nenhuma tarefa nova do agendamento pausado
+        let total = processor.get_stats().total_tasks;
+        wait_until(&processor, || {
+            processor.get_stats().total_tasks >= total + 3
+        })
+        .await;
+        assert_eq!(count_named(&processor, "Tick").await, spawned);
+
+        scheduler.resume(schedule.id).unwrap();
+        assert_eq!(scheduler.preview(schedule.id, 3).unwrap().len(), 3);
+        handles.iter().for_each(|h| h.abort());
+    }
+
+    #[tokio::test]
+    async fn test_overlap_skip_and_queue() {
+        let (processor, scheduler, handles) = setup();
+        // Tarefas bem mais longas que o intervalo: os disparos seguintes encontram
+        // a anterior ainda ativa
+        let skip = scheduler
+            .create(request(20, 300, OverlapPolicy::Skip))
+            .unwrap();
+        let queue = scheduler
+            .create(request(20, 300, OverlapPolicy::Queue))
+            .unwrap();
+
+        wait_until(&processor, || {
+            let skipped = scheduler.get(skip.id).unwrap().last_run;
+            skipped.is_some_and(|run| run.outcome == ScheduleRunOutcome::Skipped)
+                && scheduler.get(queue.id).unwrap().queued
+        })
+        .await;
+        assert_eq!(
+            processor
+                .list_tasks(&TaskQuery::default())
+                .await
+                .tasks
+                .len(),
+            2
+        );
+
+        // A primeira tarefa da fila `queue` termina e libera a execução guardada
+        let first = scheduler.get(queue.id).unwrap().active_task_id.unwrap();
+        let active = || scheduler.get(queue.id).unwrap().active_task_id.unwrap();
+        wait_until(&processor, || {
+            active() != first
+                && processor
+                    .find_task(active())
+                    .is_some_and(|task| task.status == TaskStatus::Processing)
+        })
+        .await;
+        let first = processor.get_task(first).await.unwrap();
+        assert_eq!(first.status, TaskStatus::Completed);
+        handles.iter().for_each(|h| h.abort());
+    }
+
+    #[tokio::test]
+    async fn test_schedules_survive_reopen() {
+        let path = std::env::temp_dir().join(format!("schedules-{}.json", Uuid::new_v4()));
+        let processor = Arc::new(TaskProcessor::new());
+        let id = {
+            let scheduler = Scheduler::open(processor.clone(), Some(path.as_path())).unwrap();
+            let mut request = request(60_000, 10, OverlapPolicy::Allow);
+            request.trigger = ScheduleTrigger::Cron {
+                expression: "*/5 * * * *".to_string(),
+            };
+            let schedule = scheduler.create(request).unwrap();
+            scheduler.pause(schedule.id).unwrap();
+            schedule.id
+        };
+
+        let scheduler = Scheduler::open(processor, Some(path.as_path())).unwrap();
+        let schedule = scheduler.get(id).unwrap();
+        assert!(schedule.paused);
+        assert_eq!(schedule.overlap, OverlapPolicy::Allow);
+
+        scheduler.resume(id).unwrap();
+        let runs = scheduler.preview(id, 2).unwrap();
+        assert_eq!(runs[1] - runs[0], chrono::Duration::minutes(5));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::memory::TaskTable;
+use super::{StoreError, TaskStore};
+use crate::models::{Task, TaskPage, TaskQuery};
+
+// Registros acima de `vivos * 2 + COMPACT_MIN_RECORDS` disparam a compactação do log
+const COMPACT_MIN_RECORDS: usize = 10_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum LogRecord {
+    Put { task: Box<Task> },
+    Delete { id: Uuid },
+}
+
+struct FileState {
+    table: TaskTable,
+    log: BufWriter<File>,
+    records: usize,
+}
+
+// Armazenamento em disco baseado em um log append-only de linhas JSON.
+// O estado completo fica em memória; o log é reaplicado na abertura.
+pub struct FileStore {
+    path: PathBuf,
+    state: Mutex<FileState>,
+}
+
+impl FileStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let table = Self::replay(&path)?;
+        info!(
+            "💾 Log de tarefas carregado: {} ({} tarefas)",
+            path.display(),
+            table.len()
+        );
+
+        // Reescrever o log só com o estado atual antes de voltar a anexar
+        let log = Self::write_snapshot(&path, &table)?;
+        let records = table.len();
+
+        Ok(Self {
+            path,
+            state: Mutex::new(FileState {
+                table,
+                log,
+                records,
+            }),
+        })
+    }
+
+    fn replay(path: &Path) -> Result<TaskTable, StoreError> {
+        let mut table = TaskTable::default();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(table),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut lines = BufReader::new(file).lines().peekable();
+        while let Some(line) = lines.next() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<LogRecord>(&line) {
+                Ok(LogRecord::Put { task }) => table.upsert(*task),
+                Ok(LogRecord::Delete { id }) => {
+                    table.remove(id);
+                }
+                // Última linha incompleta: o processo caiu no meio de uma escrita
+                Err(e) if lines.peek().is_none() => {
+                    warn!("⚠️ Ignorando registro final incompleto no log: {}", e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(table)
+    }
+
+    fn write_snapshot(path: &Path, table: &TaskTable) -> Result<BufWriter<File>, StoreError> {
+        let tmp = path.with_extension("compact");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp)?);
+            for task in table.values() {
+                let record = LogRecord::Put {
+                    task: Box::new(task.clone()),
+                };
+                serde_json::to_writer(&mut writer, &record)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+        }
+        fs::rename(&tmp, path)?;
+
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(BufWriter::new(file))
+    }
+
+    fn append(&self, state: &mut FileState, record: &LogRecord) -> Result<(), StoreError> {
+        self.append_all(state, std::slice::from_ref(record))
+    }
+
+    // Grava os registros com uma única descarga do buffer. Chamado antes de
+    // alterar a tabela: uma falha de escrita não deixa a memória à frente do disco.
+    fn append_all(&self, state: &mut FileState, records: &[LogRecord]) -> Result<(), StoreError> {
+        let start = state.log.get_ref().metadata()?.len();
+        if let Err(e) = Self::write_records(&mut state.log, records) {
+            // Descarta o que ficou no buffer e o que chegou ao arquivo: os
+            // registros recusados não podem reaparecer na próxima gravação
+            let file = state.log.get_ref().try_clone()?;
+            let (failed, _) = std::mem::replace(&mut state.log, BufWriter::new(file)).into_parts();
+            failed.set_len(start)?;
+            return Err(e);
+        }
+        state.records += records.len();
+        Ok(())
+    }
+
+    fn write_records(log: &mut BufWriter<File>, records: &[LogRecord]) -> Result<(), StoreError> {
+        for record in records {
+            serde_json::to_writer(&mut *log, record)?;
+            log.write_all(b"\n")?;
+        }
+        log.flush()?;
+        Ok(())
+    }
+
+    // Chamado depois de alterar a tabela, para o snapshot incluir a alteração.
+    // A alteração já está no log; uma falha aqui só adia a compactação.
+    fn compact_if_needed(&self, state: &mut FileState) {
+        if state.records <= state.table.len() * 2 + COMPACT_MIN_RECORDS {
+            return;
+        }
+        match Self::write_snapshot(&self.path, &state.table) {
+            Ok(log) => {
+                state.log = log;
+                state.records = state.table.len();
+            }
+            Err(e) => warn!("⚠️ Falha ao compactar o log de tarefas: {}", e),
+        }
+    }
+}
+
+impl TaskStore for FileStore {
+    fn insert(&self, task: Task) -> Result<(), StoreError> {
+        let mut state = self.state.lock().unwrap();
+        state.table.check_new(std::slice::from_ref(&task))?;
+        self.append(
+            &mut state,
+            &LogRecord::Put {
+                task: Box::new(task.clone()),
+            },
+        )?;
+        state.table.insert(task)?;
+        self.compact_if_needed(&mut state);
+        Ok(())
+    }
+
+    fn insert_many(&self, tasks: Vec<Task>) -> Result<(), StoreError> {
+        let mut state = self.state.lock().unwrap();
+        let records: Vec<LogRecord> = tasks
+            .iter()
+            .map(|task| LogRecord::Put {
+                task: Box::new(task.clone()),
+            })
+            .collect();
+        state.table.check_new(&tasks)?;
+        self.append_all(&mut state, &records)?;
+        state.table.insert_many(tasks)?;
+        self.compact_if_needed(&mut state);
+        Ok(())
+    }
+
+    fn get(&self, id: Uuid) -> Option<Task> {
+        self.state.lock().unwrap().table.get(id).cloned()
+    }
+
+    fn list(&self) -> Vec<Task> {
+        self.state.lock().unwrap().table.values().cloned().collect()
+    }
+
+    fn query(&self, query: &TaskQuery) -> TaskPage {
+        self.state.lock().unwrap().table.query(query)
+    }
+
+    fn update(
+        &self,
+        id: Uuid,
+        update: &mut dyn FnMut(&mut Task) -> bool,
+    ) -> Result<Option<Task>, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        // A closure altera uma cópia; a tabela só muda depois da gravação no log
+        let Some(mut task) = state.table.get(id).cloned() else {
+            return Ok(None);
+        };
+        if !update(&mut task) {
+            return Ok(state.table.get(id).cloned());
+        }
+
+        self.append(
+            &mut state,
+            &LogRecord::Put {
+                task: Box::new(task.clone()),
+            },
+        )?;
+        state.table.upsert(task.clone());
+        self.compact_if_needed(&mut state);
+        Ok(Some(task))
+    }
+
+    fn remove(&self, id: Uuid) -> Result<Option<Task>, StoreError> {
+        let mut state = self.state.lock().unwrap();
+        if state.table.get(id).is_none() {
+            return Ok(None);
+        }
+        self.append(&mut state, &LogRecord::Delete { id })?;
+        let removed = state.table.remove(id);
+        self.compact_if_needed(&mut state);
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateTaskRequest, TaskPriority, TaskStatus};
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("task-store-{}.log", Uuid::new_v4()))
+    }
+
+    fn task(name: &str) -> Task {
+        Task::from_request(CreateTaskRequest::new(name, 100, TaskPriority::Medium))
+    }
+
+    #[test]
+    fn test_reopen_restores_latest_state() {
+        let path = temp_path();
+        let (a, b) = (task("A"), task("B"));
+        {
+            let store = FileStore::open(&path).unwrap();
+            store.insert(a.clone()).unwrap();
+            store.insert(b.clone()).unwrap();
+            store
+                .update(a.id, &mut |t| {
+                    t.mark_as_processing();
+                    true
+                })
+                .unwrap();
+            // Alteração não confirmada pela closure é descartada
+            let rejected = store
+                .update(b.id, &mut |t| {
+                    t.mark_as_cancelled();
+                    false
+                })
+                .unwrap();
+            assert_eq!(rejected.unwrap().status, TaskStatus::Pending);
+            assert_eq!(store.get(b.id).unwrap().status, TaskStatus::Pending);
+        }
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.list().len(), 2);
+        assert_eq!(store.get(a.id).unwrap().status, TaskStatus::Processing);
+        assert_eq!(store.get(b.id).unwrap().status, TaskStatus::Pending);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_batch_insert_is_all_or_nothing() {
+        let path = temp_path();
+        let (a, b) = (task("A"), task("B"));
+        {
+            let store = FileStore::open(&path).unwrap();
+            store.insert_many(vec![a.clone(), b.clone()]).unwrap();
+            assert!(matches!(
+                store.insert_many(vec![task("C"), a.clone()]),
+                Err(StoreError::Duplicate(id)) if id == a.id
+            ));
+            assert_eq!(store.list().len(), 2);
+        }
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.list().len(), 2);
+        assert!(store.get(b.id).is_some());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_append_leaves_memory_unchanged() {
+        let path = temp_path();
+        let (a, b) = (task("A"), task("B"));
+        let store = FileStore::open(&path).unwrap();
+        store.insert(a.clone()).unwrap();
+        // Log aberto só para leitura: toda gravação falha
+        store.state.lock().unwrap().log = BufWriter::new(File::open(&path).unwrap());
+
+        assert!(matches!(store.insert(b.clone()), Err(StoreError::Io(_))));
+        assert!(store.insert_many(vec![b.clone(), task("C")]).is_err());
+        assert!(store
+            .update(a.id, &mut |t| {
+                t.mark_as_processing();
+                true
+            })
+            .is_err());
+        assert!(store.remove(a.id).is_err());
+
+        assert_eq!(store.list().len(), 1);
+        assert_eq!(store.get(a.id).unwrap().status, TaskStatus::Pending);
+        assert!(store.get(b.id).is_none());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_removed_task_stays_removed_after_reopen() {
+        let path = temp_path();
+        let (a, b) = (task("A"), task("B"));
+        {
+            let store = FileStore::open(&path).unwrap();
+            store.insert(a.clone()).unwrap();
+            store.insert(b.clone()).unwrap();
+            assert!(store.remove(b.id).unwrap().is_some());
+            assert!(store.remove(b.id).unwrap().is_none());
+        }
+
+        let store = FileStore::open(&path).unwrap();
+        assert!(store.get(a.id).is_some());
+        assert!(store.get(b.id).is_none());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_truncated_last_record_is_ignored() {
+        let path = temp_path();
+        let a = task("A");
+        {
+            let store = FileStore::open(&path).unwrap();
+            store.insert(a.clone()).unwrap();
+        }
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"op":"put","task":{"id":"#).unwrap();
+
+        let store = FileStore::open(&path).unwrap();
+        assert!(store.get(a.id).is_some());
+        assert_eq!(store.list().len(), 1);
+        fs::remove_file(path).unwrap();
+    }
+}
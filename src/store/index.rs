@@ -0,0 +1,458 @@
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+use uuid::Uuid;
+
+use crate::models::{SortField, SortOrder, Task, TaskFilter, TaskPriority, TaskQuery, TaskStatus};
+
+type Entry = (DateTime<Utc>, Uuid);
+// `(completed_at, created_at, id)`: mesma ordem da ordenação por conclusão,
+// com as tarefas não concluídas no início
+type CompletedEntry = (Option<DateTime<Utc>>, DateTime<Utc>, Uuid);
+type Ids<'a> = Box<dyn Iterator<Item = Uuid> + 'a>;
+
+// Índices secundários da tabela de tarefas, usados pela listagem para não
+// percorrer todas as tarefas. Cada entrada é `(created_at, id)`, exceto em
+// `by_completed`, que guarda todas as tarefas ordenadas por `completed_at`.
+#[derive(Debug, Default)]
+pub(crate) struct TaskIndexes {
+    by_created: BTreeSet<Entry>,
+    by_completed: BTreeSet<CompletedEntry>,
+    completed: usize,
+    by_tenant: HashMap<String, BTreeSet<Entry>>,
+    by_workflow: HashMap<Uuid, BTreeSet<Entry>>,
+    by_status: HashMap<TaskStatus, BTreeSet<Entry>>,
+    by_priority: HashMap<TaskPriority, BTreeSet<Entry>>,
+    by_label: HashMap<(String, String), BTreeSet<Entry>>,
+}
+
+impl TaskIndexes {
+    pub(crate) fn add(&mut self, task: &Task) {
+        let entry = (task.created_at, task.id);
+        self.by_created.insert(entry);
+        self.by_completed
+            .insert((task.completed_at, task.created_at, task.id));
+        if task.completed_at.is_some() {
+            self.completed += 1;
+        }
+        self.by_tenant
+            .entry(task.tenant.clone())
+            .or_default()
+            .insert(entry);
+        if let Some(workflow_id) = task.workflow_id {
+            self.by_workflow
+                .entry(workflow_id)
+                .or_default()
+                .insert(entry);
+        }
+        self.by_status
+            .entry(task.status.clone())
+            .or_default()
+            .insert(entry);
+        self.by_priority
+            .entry(task.priority.clone())
+            .or_default()
+            .insert(entry);
+        for (key, value) in &task.labels {
+            self.by_label
+                .entry((key.clone(), value.clone()))
+                .or_default()
+                .insert(entry);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, task: &Task) {
+        let entry = (task.created_at, task.id);
+        self.by_created.remove(&entry);
+        if self
+            .by_completed
+            .remove(&(task.completed_at, task.created_at, task.id))
+            && task.completed_at.is_some()
+        {
+            self.completed -= 1;
+        }
+        if let Some(set) = self.by_tenant.get_mut(&task.tenant) {
+            set.remove(&entry);
+            if set.is_empty() {
+                self.by_tenant.remove(&task.tenant);
+            }
+        }
+        if let Some(workflow_id) = task.workflow_id {
+            if let Some(set) = self.by_workflow.get_mut(&workflow_id) {
+                set.remove(&entry);
+                if set.is_empty() {
+                    self.by_workflow.remove(&workflow_id);
+                }
+            }
+        }
+        if let Some(set) = self.by_status.get_mut(&task.status) {
+            set.remove(&entry);
+        }
+        if let Some(set) = self.by_priority.get_mut(&task.priority) {
+            set.remove(&entry);
+        }
+        for (key, value) in &task.labels {
+            let label = (key.clone(), value.clone());
+            if let Some(set) = self.by_label.get_mut(&label) {
+                set.remove(&entry);
+                if set.is_empty() {
+                    self.by_label.remove(&label);
+                }
+            }
+        }
+    }
+
+    // Ids candidatos segundo o índice mais seletivo entre os que o filtro usa.
+    // O chamador ainda aplica o filtro completo; `None` significa que nenhum
+    // índice se aplica (somente busca por nome ou rótulo sem valor).
+    pub(crate) fn candidates(&self, filter: &TaskFilter) -> Option<Vec<Uuid>> {
+        self.options(filter)
+            .into_iter()
+            .min_by_key(|(size, _)| *size)
+            .map(|(_, ids)| ids.collect())
+    }
+
+    // Ids já na ordem pedida, a partir do cursor, quando a ordenação é por uma
+    // data indexada. O chamador aplica o filtro e para ao completar a página.
+    // `None` quando é melhor ordenar os candidatos: ordenação por prioridade ou
+    // nome, ou um filtro tão seletivo que percorrer o índice em ordem visitaria
+    // mais tarefas do que ele devolve.
+    pub(crate) fn ordered(&self, query: &TaskQuery) -> Option<Ids<'_>> {
+        let filter = &query.filter;
+        let cursor = query.after.as_ref().and_then(|cursor| cursor.time_key());
+        let desc = query.order == SortOrder::Desc;
+        let (size, ids) = match query.sort {
+            // O índice do tenant tem a mesma ordem e só as tarefas dele
+            SortField::CreatedAt => {
+                let set = filter
+                    .tenant
+                    .as_ref()
+                    .and_then(|tenant| self.by_tenant.get(tenant))
+                    .unwrap_or(&self.by_created);
+                walk(
+                    set,
+                    filter.created_after.map(|at| (at, Uuid::nil())),
+                    filter.created_before.map(|at| (at, Uuid::nil())),
+                    cursor.map(|(_, created_at, id)| (created_at, id)),
+                    desc,
+                    estimate_created(set, filter.created_after, filter.created_before),
+                    |(_, id)| *id,
+                )
+            }
+            SortField::CompletedAt => {
+                let bounded = filter.completed_after.is_some() || filter.completed_before.is_some();
+                // Com limites de conclusão, as tarefas não concluídas ficam de fora
+                let after = match filter.completed_after {
+                    Some(at) => Some(at),
+                    None if bounded => Some(DateTime::<Utc>::MIN_UTC),
+                    None => None,
+                };
+                walk(
+                    &self.by_completed,
+                    after.map(completed_bound),
+                    filter.completed_before.map(completed_bound),
+                    cursor,
+                    desc,
+                    if bounded {
+                        self.estimate_completed(filter.completed_after, filter.completed_before)
+                    } else {
+                        self.by_completed.len()
+                    },
+                    |(_, _, id)| *id,
+                )
+            }
+            SortField::Priority | SortField::Name => return None,
+        };
+
+        // Custo de percorrer em ordem: até completar a página, proporcional à
+        // fração do índice percorrido que o filtro mais seletivo aceita
+        let best = self.options(filter).into_iter().map(|(size, _)| size).min();
+        match (best, query.limit) {
+            (None, _) => Some(ids),
+            (Some(0), _) | (Some(_), None) => None,
+            (Some(best), Some(limit)) => {
+                let wanted = limit.saturating_add(1).min(best);
+                let visits = wanted.saturating_mul(size) / best;
+                (visits <= best).then_some(ids)
+            }
+        }
+    }
+
+    // Total exato sem percorrer as tarefas, quando o filtro não passa do tenant
+    pub(crate) fn count(&self, filter: &TaskFilter) -> Option<usize> {
+        if !filter.is_tenant_only() {
+            return None;
+        }
+        Some(match &filter.tenant {
+            Some(tenant) => self.by_tenant.get(tenant).map_or(0, BTreeSet::len),
+            None => self.by_created.len(),
+        })
+    }
+
+    fn options(&self, filter: &TaskFilter) -> Vec<(usize, Ids<'_>)> {
+        let mut options: Vec<(usize, Ids<'_>)> = Vec::new();
+
+        if let Some(tenant) = &filter.tenant {
+            options.push(union(self.by_tenant.get(tenant).into_iter().collect()));
+        }
+        if let Some(workflow_id) = &filter.workflow_id {
+            options.push(union(
+                self.by_workflow.get(workflow_id).into_iter().collect(),
+            ));
+        }
+
+        if !filter.statuses.is_empty() {
+            let sets: Vec<_> = filter
+                .statuses
+                .iter()
+                .filter_map(|status| self.by_status.get(status))
+                .collect();
+            options.push(union(sets));
+        }
+        if !filter.priorities.is_empty() {
+            let sets: Vec<_> = filter
+                .priorities
+                .iter()
+                .filter_map(|priority| self.by_priority.get(priority))
+                .collect();
+            options.push(union(sets));
+        }
+        for (key, value) in &filter.labels {
+            if let Some(value) = value {
+                match self.by_label.get(&(key.clone(), value.clone())) {
+                    Some(set) => options.push(union(vec![set])),
+                    None => options.push((0, Box::new(std::iter::empty()))),
+                }
+            }
+        }
+        if filter.created_after.is_some() || filter.created_before.is_some() {
+            let (_, ids) = walk(
+                &self.by_created,
+                filter.created_after.map(|at| (at, Uuid::nil())),
+                filter.created_before.map(|at| (at, Uuid::nil())),
+                None,
+                false,
+                0,
+                |(_, id)| *id,
+            );
+            let size = estimate_created(
+                &self.by_created,
+                filter.created_after,
+                filter.created_before,
+            );
+            options.push((size, ids));
+        }
+        if filter.completed_after.is_some() || filter.completed_before.is_some() {
+            let after = filter.completed_after.unwrap_or(DateTime::<Utc>::MIN_UTC);
+            let (_, ids) = walk(
+                &self.by_completed,
+                Some(completed_bound(after)),
+                filter.completed_before.map(completed_bound),
+                None,
+                false,
+                0,
+                |(_, _, id)| *id,
+            );
+            let size = self.estimate_completed(filter.completed_after, filter.completed_before);
+            options.push((size, ids));
+        }
+
+        options
+    }
+
+    // Estimativas do tamanho de um intervalo de datas, supondo as datas
+    // distribuídas por igual entre a primeira e a última do índice; contar as
+    // entradas exigiria percorrer o intervalo inteiro
+    fn estimate_completed(
+        &self,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> usize {
+        let first = self
+            .by_completed
+            .range(completed_bound(DateTime::<Utc>::MIN_UTC)..)
+            .next()
+            .and_then(|(at, _, _)| *at);
+        let last = self.by_completed.last().and_then(|(at, _, _)| *at);
+        estimate(self.completed, first, last, after, before)
+    }
+}
+
+fn estimate_created(
+    set: &BTreeSet<Entry>,
+    after: Option<DateTime<Utc>>,
+    before: Option<DateTime<Utc>>,
+) -> usize {
+    let first = set.first().map(|(at, _)| *at);
+    let last = set.last().map(|(at, _)| *at);
+    estimate(set.len(), first, last, after, before)
+}
+
+fn completed_bound(at: DateTime<Utc>) -> CompletedEntry {
+    (Some(at), DateTime::<Utc>::MIN_UTC, Uuid::nil())
+}
+
+fn union(sets: Vec<&BTreeSet<Entry>>) -> (usize, Ids<'_>) {
+    let size = sets.iter().map(|set| set.len()).sum();
+    (
+        size,
+        Box::new(sets.into_iter().flatten().map(|(_, id)| *id)),
+    )
+}
+
+// Percorre as entradas em `[after, before)` que vêm depois do cursor na ordem
+// pedida; o tamanho devolvido é só repassado de `size`
+fn walk<K: Ord + Copy>(
+    set: &BTreeSet<K>,
+    after: Option<K>,
+    before: Option<K>,
+    cursor: Option<K>,
+    desc: bool,
+    size: usize,
+    id: fn(&K) -> Uuid,
+) -> (usize, Ids<'_>) {
+    let mut start = after.map_or(Bound::Unbounded, Bound::Included);
+    let mut end = before.map_or(Bound::Unbounded, Bound::Excluded);
+    if let Some(cursor) = cursor {
+        if desc {
+            if before.is_none_or(|before| cursor <= before) {
+                end = Bound::Excluded(cursor);
+            }
+        } else if after.is_none_or(|after| cursor >= after) {
+            start = Bound::Excluded(cursor);
+        }
+    }
+    // `BTreeSet::range` entra em pânico com intervalos invertidos
+    let empty = match (start, end) {
+        (Bound::Included(s), Bound::Excluded(e)) | (Bound::Excluded(s), Bound::Excluded(e)) => {
+            s >= e
+        }
+        _ => false,
+    };
+    if empty {
+        return (0, Box::new(std::iter::empty()));
+    }
+    let entries = set.range((start, end));
+    if desc {
+        (size, Box::new(entries.rev().map(id)))
+    } else {
+        (size, Box::new(entries.map(id)))
+    }
+}
+
+fn estimate(
+    len: usize,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+    after: Option<DateTime<Utc>>,
+    before: Option<DateTime<Utc>>,
+) -> usize {
+    let (Some(first), Some(last)) = (first, last) else {
+        return 0;
+    };
+    let from = after.map_or(first, |at| at.max(first));
+    let to = before.map_or(last, |at| at.min(last));
+    if from > to || before.is_some_and(|at| at <= first) {
+        return 0;
+    }
+    let span = (last - first).num_milliseconds();
+    if span <= 0 {
+        return len;
+    }
+    let covered = (to - from).num_milliseconds() as f64 / span as f64;
+    ((len as f64 * covered).ceil() as usize).clamp(1, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateTaskRequest;
+
+    fn task(name: &str, priority: TaskPriority, labels: &[(&str, &str)]) -> Task {
+        let mut request = CreateTaskRequest::new(name, 10, priority);
+        request.labels = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Task::from_request(request)
+    }
+
+    #[test]
+    fn test_candidates_use_most_selective_index() {
+        let mut indexes = TaskIndexes::default();
+        let tasks: Vec<Task> = (0..10)
+            .map(|i| {
+                let labels: &[(&str, &str)] = if i == 3 { &[("env", "prod")] } else { &[] };
+                task(&format!("t{}", i), TaskPriority::Low, labels)
+            })
+            .collect();
+        for task in &tasks {
+            indexes.add(task);
+        }
+
+        let filter = TaskFilter {
+            statuses: vec![TaskStatus::Pending],
+            labels: vec![("env".into(), Some("prod".into()))],
+            ..Default::default()
+        };
+        assert_eq!(indexes.candidates(&filter), Some(vec![tasks[3].id]));
+
+        let filter = TaskFilter {
+            labels: vec![("env".into(), Some("dev".into()))],
+            ..Default::default()
+        };
+        assert_eq!(indexes.candidates(&filter), Some(Vec::new()));
+        assert_eq!(indexes.candidates(&TaskFilter::default()), None);
+
+        indexes.remove(&tasks[3]);
+        let filter = TaskFilter {
+            labels: vec![("env".into(), Some("prod".into()))],
+            ..Default::default()
+        };
+        assert_eq!(indexes.candidates(&filter), Some(Vec::new()));
+        assert!(indexes.by_label.is_empty());
+    }
+
+    #[test]
+    fn test_tenant_and_workflow_indexes() {
+        let mut indexes = TaskIndexes::default();
+        let workflow_id = Uuid::new_v4();
+        let tasks: Vec<Task> = (0..6)
+            .map(|i| {
+                let mut task = task(&format!("t{}", i), TaskPriority::Low, &[]);
+                task.tenant = if i < 2 { "a" } else { "b" }.to_string();
+                task.workflow_id = (i == 4).then_some(workflow_id);
+                task
+            })
+            .collect();
+        for task in &tasks {
+            indexes.add(task);
+        }
+
+        let tenant = |name: &str| TaskFilter {
+            tenant: Some(name.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(indexes.count(&tenant("a")), Some(2));
+        assert_eq!(indexes.count(&tenant("c")), Some(0));
+        let mut ids = indexes.candidates(&tenant("a")).unwrap();
+        ids.sort();
+        let mut expected = vec![tasks[0].id, tasks[1].id];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        let filter = TaskFilter {
+            workflow_id: Some(workflow_id),
+            ..tenant("b")
+        };
+        assert_eq!(indexes.count(&filter), None);
+        assert_eq!(indexes.candidates(&filter), Some(vec![tasks[4].id]));
+
+        indexes.remove(&tasks[4]);
+        assert_eq!(indexes.candidates(&filter), Some(Vec::new()));
+        assert!(indexes.by_workflow.is_empty());
+        indexes.remove(&tasks[0]);
+        indexes.remove(&tasks[1]);
+        assert!(!indexes.by_tenant.contains_key("a"));
+    }
+}
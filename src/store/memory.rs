@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use uuid::Uuid;
+
+use super::index::TaskIndexes;
+use super::{StoreError, TaskStore};
+use crate::models::{Task, TaskFilter, TaskPage, TaskQuery};
+
+// Tabela de tarefas em memória, compartilhada pelos dois backends
+#[derive(Debug, Default)]
+pub(crate) struct TaskTable {
+    tasks: HashMap<Uuid, Task>,
+    indexes: TaskIndexes,
+}
+
+impl TaskTable {
+    pub(crate) fn insert(&mut self, task: Task) -> Result<(), StoreError> {
+        if self.tasks.contains_key(&task.id) {
+            return Err(StoreError::Duplicate(task.id));
+        }
+        self.indexes.add(&task);
+        self.tasks.insert(task.id, task);
+        Ok(())
+    }
+
+    pub(crate) fn insert_many(&mut self, tasks: Vec<Task>) -> Result<(), StoreError> {
+        self.check_new(&tasks)?;
+        for task in tasks {
+            self.indexes.add(&task);
+            self.tasks.insert(task.id, task);
+        }
+        Ok(())
+    }
+
+    // Falha se algum id já existe na tabela ou se repete no lote
+    pub(crate) fn check_new(&self, tasks: &[Task]) -> Result<(), StoreError> {
+        let mut ids = HashSet::new();
+        match tasks
+            .iter()
+            .find(|task| self.tasks.contains_key(&task.id) || !ids.insert(task.id))
+        {
+            Some(task) => Err(StoreError::Duplicate(task.id)),
+            None => Ok(()),
+        }
+    }
+
+    // Grava a versão nova da tarefa; na reconstrução a partir do log, o
+    // último registro prevalece
+    pub(crate) fn upsert(&mut self, task: Task) {
+        if let Some(previous) = self.tasks.remove(&task.id) {
+            self.indexes.remove(&previous);
+        }
+        self.indexes.add(&task);
+        self.tasks.insert(task.id, task);
+    }
+
+    pub(crate) fn get(&self, id: Uuid) -> Option<&Task> {
+        self.tasks.get(&id)
+    }
+
+    pub(crate) fn remove(&mut self, id: Uuid) -> Option<Task> {
+        let task = self.tasks.remove(&id)?;
+        self.indexes.remove(&task);
+        Some(task)
+    }
+
+    // Filtra a partir do índice mais seletivo; sem índice aplicável, percorre a tabela
+    pub(crate) fn query(&self, query: &TaskQuery) -> TaskPage {
+        let filter = &query.filter;
+        // Ordenação por data: o índice já entrega a ordem, basta parar ao
+        // completar a página
+        if let Some(ids) = self.indexes.ordered(query) {
+            let wanted = query
+                .limit
+                .map_or(usize::MAX, |limit| limit.saturating_add(1));
+            let tasks = ids
+                .filter_map(|id| self.tasks.get(&id))
+                .filter(|task| filter.matches(task))
+                .take(wanted)
+                .cloned()
+                .collect();
+            return query.page(tasks, self.count(filter));
+        }
+
+        let matches = self
+            .candidates(filter)
+            .filter(|task| filter.matches(task))
+            .cloned()
+            .collect();
+        query.paginate(matches)
+    }
+
+    // Total de tarefas que passam no filtro, sem copiar nem ordenar
+    fn count(&self, filter: &TaskFilter) -> usize {
+        if let Some(count) = self.indexes.count(filter) {
+            return count;
+        }
+        self.candidates(filter)
+            .filter(|task| filter.matches(task))
+            .count()
+    }
+
+    fn candidates(&self, filter: &TaskFilter) -> Box<dyn Iterator<Item = &Task> + '_> {
+        match self.indexes.candidates(filter) {
+            Some(ids) => Box::new(ids.into_iter().filter_map(|id| self.tasks.get(&id))),
+            None => Box::new(self.tasks.values()),
+        }
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.values()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.tasks.len()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    table: RwLock<TaskTable>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TaskStore for MemoryStore {
+    fn insert(&self, task: Task) -> Result<(), StoreError> {
+        self.table.write().unwrap().insert(task)
+    }
+
+    fn insert_many(&self, tasks: Vec<Task>) -> Result<(), StoreError> {
+        self.table.write().unwrap().insert_many(tasks)
+    }
+
+    fn get(&self, id: Uuid) -> Option<Task> {
+        self.table.read().unwrap().get(id).cloned()
+    }
+
+    fn list(&self) -> Vec<Task> {
+        self.table.read().unwrap().values().cloned().collect()
+    }
+
+    fn query(&self, query: &TaskQuery) -> TaskPage {
+        self.table.read().unwrap().query(query)
+    }
+
+    fn update(
+        &self,
+        id: Uuid,
+        update: &mut dyn FnMut(&mut Task) -> bool,
+    ) -> Result<Option<Task>, StoreError> {
+        let mut table = self.table.write().unwrap();
+        // A closure altera uma cópia; a tabela só muda se ela confirmar
+        let Some(mut task) = table.get(id).cloned() else {
+            return Ok(None);
+        };
+        if !update(&mut task) {
+            return Ok(table.get(id).cloned());
+        }
+        table.upsert(task.clone());
+        Ok(Some(task))
+    }
+
+    fn remove(&self, id: Uuid) -> Result<Option<Task>, StoreError> {
+        Ok(self.table.write().unwrap().remove(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateTaskRequest, SortField, SortOrder, TaskPriority, TaskStatus};
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn test_rejected_update_is_discarded() {
+        let store = MemoryStore::new();
+        let task = Task::from_request(CreateTaskRequest::new("t", 10, TaskPriority::Low));
+        store.insert(task.clone()).unwrap();
+
+        let returned = store
+            .update(task.id, &mut |t| {
+                t.mark_as_cancelled();
+                false
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(returned.status, TaskStatus::Pending);
+        assert_eq!(store.get(task.id).unwrap().status, TaskStatus::Pending);
+        let filter = TaskFilter {
+            statuses: vec![TaskStatus::Cancelled],
+            ..Default::default()
+        };
+        assert_eq!(store.table.read().unwrap().count(&filter), 0);
+    }
+
+    #[test]
+    fn test_ordered_listing_matches_sorted_listing() {
+        let base = Utc::now();
+        let mut table = TaskTable::default();
+        for i in 0..40i64 {
+            let mut task = Task::from_request(CreateTaskRequest::new(
+                format!("t{}", i),
+                10,
+                TaskPriority::Low,
+            ));
+            // Datas repetidas forçam o desempate por id
+            task.created_at = base + Duration::minutes(i / 2);
+            task.tenant = if i % 4 == 1 { "a" } else { "b" }.to_string();
+            if i % 3 == 0 {
+                task.status = TaskStatus::Completed;
+                task.completed_at = Some(base + Duration::minutes(60 - i / 4));
+            }
+            table.insert(task).unwrap();
+        }
+        let all: Vec<Task> = table.values().cloned().collect();
+
+        let filters = [
+            TaskFilter::default(),
+            TaskFilter {
+                created_after: Some(base + Duration::minutes(5)),
+                created_before: Some(base + Duration::minutes(15)),
+                ..Default::default()
+            },
+            TaskFilter {
+                completed_before: Some(base + Duration::minutes(58)),
+                ..Default::default()
+            },
+            TaskFilter {
+                statuses: vec![TaskStatus::Pending],
+                ..Default::default()
+            },
+            TaskFilter {
+                tenant: Some("a".to_string()),
+                created_after: Some(base + Duration::minutes(3)),
+                ..Default::default()
+            },
+        ];
+        for filter in filters {
+            for sort in [SortField::CreatedAt, SortField::CompletedAt] {
+                for order in [SortOrder::Asc, SortOrder::Desc] {
+                    let mut query = TaskQuery {
+                        filter: filter.clone(),
+                        sort,
+                        order,
+                        after: None,
+                        limit: Some(4),
+                    };
+                    loop {
+                        let matches = all.iter().filter(|t| filter.matches(t)).cloned();
+                        let expected = query.paginate(matches.collect());
+                        let page = table.query(&query);
+                        let ids = |page: &TaskPage| -> Vec<Uuid> {
+                            page.tasks.iter().map(|t| t.id).collect()
+                        };
+                        assert_eq!(ids(&page), ids(&expected));
+                        assert_eq!(page.total, expected.total);
+                        match page.next_cursor {
+                            Some(cursor) => query.after = Some(cursor),
+                            None => break,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
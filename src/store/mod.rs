@@ -0,0 +1,65 @@
+pub mod file;
+mod index;
+pub mod memory;
+
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::config::{StoreBackend, StoreConfig};
+use crate::models::{Task, TaskPage, TaskQuery};
+
+pub use file::FileStore;
+pub use memory::MemoryStore;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("tarefa já existe: {0}")]
+    Duplicate(Uuid),
+    #[error("erro de E/S no armazenamento: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("registro inválido no log: {0}")]
+    Corrupt(#[from] serde_json::Error),
+}
+
+// Armazenamento de tarefas usado pelo processador
+pub trait TaskStore: Send + Sync {
+    fn insert(&self, task: Task) -> Result<(), StoreError>;
+
+    // Grava todas as tarefas sob um único lock; em caso de erro, nenhuma é inserida
+    fn insert_many(&self, tasks: Vec<Task>) -> Result<(), StoreError>;
+
+    fn get(&self, id: Uuid) -> Option<Task>;
+
+    fn list(&self) -> Vec<Task>;
+
+    // Listagem filtrada, ordenada e paginada, apoiada nos índices da tabela
+    fn query(&self, query: &TaskQuery) -> TaskPage;
+
+    // Aplica `update` à tarefa sob o lock do armazenamento; a alteração só é
+    // persistida quando a closure retorna `true`. Retorna a tarefa resultante.
+    fn update(
+        &self,
+        id: Uuid,
+        update: &mut dyn FnMut(&mut Task) -> bool,
+    ) -> Result<Option<Task>, StoreError>;
+
+    fn remove(&self, id: Uuid) -> Result<Option<Task>, StoreError>;
+}
+
+pub fn open(config: &StoreConfig) -> Result<Arc<dyn TaskStore>, StoreError> {
+    open_at(&config.backend, &config.path)
+}
+
+// Coleção separada para tarefas que falharam definitivamente
+pub fn open_dead_letters(config: &StoreConfig) -> Result<Arc<dyn TaskStore>, StoreError> {
+    open_at(&config.backend, &config.dead_letter_path)
+}
+
+fn open_at(backend: &StoreBackend, path: &Path) -> Result<Arc<dyn TaskStore>, StoreError> {
+    match backend {
+        StoreBackend::Memory => Ok(Arc::new(MemoryStore::new())),
+        StoreBackend::File => Ok(Arc::new(FileStore::open(path)?)),
+    }
+}
@@ -0,0 +1,124 @@
+use axum::http::{HeaderMap, Request, Uri};
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
+
+use crate::config::{LogFormat, TelemetryConfig};
+
+// Logs no terminal e spans no OpenTelemetry, exportados via OTLP quando há um
+// coletor configurado. O provider devolvido deve ser encerrado na saída do
+// processo para enviar os spans pendentes.
+pub fn init(config: &TelemetryConfig) -> anyhow::Result<SdkTracerProvider> {
+    let resource = Resource::builder()
+        .with_service_name(config.service_name.clone())
+        .build();
+    let mut provider = SdkTracerProvider::builder().with_resource(resource);
+    if let Some(endpoint) = &config.otlp_endpoint {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?;
+        provider = provider.with_batch_exporter(exporter);
+    }
+    let provider = provider.build();
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    // Em JSON, cada linha traz `timestamp`, `level`, `target`, `message`, os campos
+    // do evento e `span` com os campos do span corrente (ex.: `task.id`)
+    let fmt = match config.log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "task_processor=debug,tower_http=debug".into()),
+        )
+        .with(fmt)
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("task-processor")))
+        .init();
+
+    Ok(provider)
+}
+
+// Span de cada requisição HTTP, filho do trace do cabeçalho `traceparent`, se houver
+pub fn http_span<B>(request: &Request<B>) -> Span {
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %redact(request.uri()),
+        version = ?request.version(),
+        auth.subject = tracing::field::Empty,
+        auth.tenant = tracing::field::Empty,
+    );
+    let _ = span.set_parent(extract(request.headers()));
+    span
+}
+
+// O token do WebSocket pode vir na URL; não deve parar nos logs
+fn redact(uri: &Uri) -> String {
+    match uri.query() {
+        Some(query) if query.contains("access_token=") => format!("{}?…", uri.path()),
+        _ => uri.to_string(),
+    }
+}
+
+pub fn extract(headers: &HeaderMap) -> opentelemetry::Context {
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    })
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn test_extracts_traceparent_header() {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+
+        let context = extract(&headers);
+        let span = context.span();
+        let parent = span.span_context();
+        assert!(parent.is_remote());
+        assert_eq!(
+            parent.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(parent.span_id().to_string(), "00f067aa0ba902b7");
+
+        assert!(!extract(&HeaderMap::new()).span().span_context().is_valid());
+    }
+}
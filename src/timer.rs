@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use uuid::Uuid;
+
+// Min-heap de prazos: tarefas agendadas (`run_at`) e esperas entre tentativas.
+// Entradas de tarefas canceladas não são removidas; quem consome confere o status.
+#[derive(Debug, Default)]
+pub struct TimerQueue {
+    heap: BinaryHeap<Reverse<(DateTime<Utc>, u64, Uuid)>>,
+    seq: u64,
+}
+
+impl TimerQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, at: DateTime<Utc>, id: Uuid) {
+        self.seq += 1;
+        self.heap.push(Reverse((at, self.seq, id)));
+    }
+
+    pub fn next_deadline(&self) -> Option<DateTime<Utc>> {
+        self.heap.peek().map(|Reverse((at, _, _))| *at)
+    }
+
+    // Remove e retorna, em ordem de prazo, as entradas vencidas até `now`
+    pub fn pop_due(&mut self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let mut due = Vec::new();
+        while let Some(Reverse((at, _, id))) = self.heap.peek() {
+            if *at > now {
+                break;
+            }
+            due.push(*id);
+            self.heap.pop();
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_pops_due_entries_in_deadline_order() {
+        let now = Utc::now();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut timers = TimerQueue::new();
+        timers.push(now + Duration::seconds(10), c);
+        timers.push(now - Duration::seconds(1), b);
+        timers.push(now - Duration::seconds(5), a);
+
+        assert_eq!(timers.next_deadline(), Some(now - Duration::seconds(5)));
+        assert_eq!(timers.pop_due(now), vec![a, b]);
+        assert!(timers.pop_due(now).is_empty());
+        assert_eq!(timers.pop_due(now + Duration::seconds(10)), vec![c]);
+        assert_eq!(timers.next_deadline(), None);
+    }
+}
@@ -5,30 +5,134 @@ use axum::{
     },
 };
 use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, broadcast::error::RecvError, mpsc, mpsc::error::TrySendError, Mutex};
 use tracing::{error, info, warn};
+use uuid::Uuid;
 
+use crate::auth::Tenant;
+use crate::logs::LogLine;
 use crate::AppState;
 
+// Comandos do cliente aguardando o envio; um cliente que enche a fila é desconectado
+const COMMAND_BUFFER: usize = 32;
+
+// Mensagens do cliente: tail do log de uma tarefa
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    SubscribeLogs { task_id: Uuid },
+    UnsubscribeLogs { task_id: Uuid },
+}
+
+// A conexão só recebe atualizações e logs das tarefas do seu tenant
 pub async fn handle_websocket(
     ws: WebSocket,
     State(state): State<AppState>,
+    tenant: Tenant,
 ) {
     let (sender, mut receiver) = ws.split();
     let sender = Arc::new(Mutex::new(sender));
     let mut rx = state.processor.subscribe();
+    let metrics = state.metrics.clone();
+    let (commands, mut command_rx) = mpsc::channel(COMMAND_BUFFER);
 
     info!("🔌 Nova conexão WebSocket estabelecida");
+    metrics.ws_connected();
 
     let sender_clone = sender.clone();
+    let lag_metrics = metrics.clone();
+    let processor = state.processor.clone();
     // Spawn task para enviar atualizações
     let mut send_task = tokio::spawn(async move {
-        while let Ok((_id, task)) = rx.recv().await {
-            let message = serde_json::json!({
-                "type": "task_update",
-                "task": task
-            });
+        // Só recebe linhas de log enquanto houver alguma tarefa em tail;
+        // guarda o `seq` da última linha enviada de cada uma
+        let mut log_rx = None;
+        let mut tails: HashMap<Uuid, u64> = HashMap::new();
+        loop {
+            let message = tokio::select! {
+                update = rx.recv() => match update {
+                    Ok((_id, task)) if tenant.owns(&task) => serde_json::json!({
+                        "type": "task_update",
+                        "task": task
+                    }),
+                    Ok(_) => continue,
+                    // Cliente lento: as atualizações perdidas são contadas e o envio continua
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("⚠️ Cliente WebSocket atrasado; {} atualizações descartadas", skipped);
+                        lag_metrics.ws_lagged(skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                line = next_log_line(&mut log_rx) => match line {
+                    Ok((task_id, line)) => match tails.get_mut(&task_id) {
+                        Some(last) if line.seq > *last => {
+                            *last = line.seq;
+                            serde_json::json!({
+                                "type": "task_log",
+                                "task_id": task_id,
+                                "line": line
+                            })
+                        }
+                        _ => continue,
+                    },
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("⚠️ Tail de logs atrasado; {} linhas descartadas", skipped);
+                        lag_metrics.ws_lagged(skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => {
+                        log_rx = None;
+                        continue;
+                    }
+                },
+                command = command_rx.recv() => match command {
+                    // Primeiro as linhas já registradas, depois as novas. Só assina o
+                    // canal de logs depois de confirmar que a tarefa é do tenant.
+                    Some(ClientMessage::SubscribeLogs { task_id }) => {
+                        let owned = processor
+                            .find_task(task_id)
+                            .is_some_and(|task| tenant.owns(&task));
+                        if owned && log_rx.is_none() {
+                            log_rx = Some(processor.subscribe_logs());
+                        }
+                        match processor
+                            .task_logs(task_id, None, usize::MAX)
+                            .filter(|_| owned)
+                        {
+                            Some(lines) => {
+                                tails.insert(task_id, lines.last().map_or(0, |line| line.seq));
+                                serde_json::json!({
+                                    "type": "task_logs",
+                                    "task_id": task_id,
+                                    "lines": lines
+                                })
+                            }
+                            None => {
+                                if tails.is_empty() {
+                                    log_rx = None;
+                                }
+                                serde_json::json!({
+                                    "type": "error",
+                                    "task_id": task_id,
+                                    "error": "Tarefa não encontrada"
+                                })
+                            }
+                        }
+                    }
+                    Some(ClientMessage::UnsubscribeLogs { task_id }) => {
+                        tails.remove(&task_id);
+                        if tails.is_empty() {
+                            log_rx = None;
+                        }
+                        continue;
+                    }
+                    None => break,
+                },
+            };
 
             let mut sender = sender_clone.lock().await;
             if let Err(e) = sender.send(Message::Text(
@@ -40,7 +144,7 @@ pub async fn handle_websocket(
         }
     });
 
-    // Spawn task para receber mensagens (ping/pong)
+    // Spawn task para receber mensagens (ping/pong e assinaturas de log)
     let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
             match msg {
@@ -56,6 +160,33 @@ pub async fn handle_websocket(
                         break;
                     }
                 }
+                Message::Text(text) => match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(command) => match commands.try_send(command) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(_)) => {
+                            warn!("⚠️ Cliente WebSocket enviou comandos demais; conexão encerrada");
+                            let message = serde_json::json!({
+                                "type": "error",
+                                "error": "Comandos demais sem resposta; conexão encerrada"
+                            });
+                            let mut sender = sender.lock().await;
+                            let _ = sender.send(Message::Text(message.to_string())).await;
+                            let _ = sender.send(Message::Close(None)).await;
+                            break;
+                        }
+                        Err(TrySendError::Closed(_)) => break,
+                    },
+                    Err(e) => {
+                        let message = serde_json::json!({
+                            "type": "error",
+                            "error": format!("Mensagem inválida: {}", e)
+                        });
+                        let mut sender = sender.lock().await;
+                        if sender.send(Message::Text(message.to_string())).await.is_err() {
+                            break;
+                        }
+                    }
+                },
                 _ => {}
             }
         }
@@ -70,5 +201,14 @@ pub async fn handle_websocket(
             send_task.abort();
         }
     }
+    metrics.ws_disconnected();
 }
 
+async fn next_log_line(
+    rx: &mut Option<broadcast::Receiver<(Uuid, LogLine)>>,
+) -> Result<(Uuid, LogLine), RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
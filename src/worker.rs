@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+use crate::config::{ProcessorConfig, TenantQuotas};
+use crate::models::{KindWorkerStats, WorkerStats};
+use crate::queue::Rejection;
+
+// Pool de workers com limite global e limites opcionais por tipo de tarefa e por tenant
+pub struct WorkerPool {
+    max_concurrency: usize,
+    global: Arc<Semaphore>,
+    kind_limits: HashMap<String, (usize, Arc<Semaphore>)>,
+    tenant_quotas: TenantQuotas,
+    active: Arc<Mutex<HashMap<String, usize>>>,
+    // Tarefas em execução por tenant
+    tenants: Arc<Mutex<HashMap<String, usize>>>,
+    released: Arc<Notify>,
+}
+
+// Vaga ocupada por uma tarefa em execução; liberada ao ser descartada
+pub struct WorkerPermit {
+    kind: String,
+    tenant: String,
+    active: Arc<Mutex<HashMap<String, usize>>>,
+    tenants: Arc<Mutex<HashMap<String, usize>>>,
+    released: Arc<Notify>,
+    global: Option<OwnedSemaphorePermit>,
+    kind_permit: Option<OwnedSemaphorePermit>,
+}
+
+impl WorkerPool {
+    // `released` é notificado sempre que uma vaga é devolvida ao pool
+    pub fn new(config: &ProcessorConfig, released: Arc<Notify>) -> Self {
+        let kind_limits = config
+            .kind_concurrency
+            .iter()
+            .map(|(kind, limit)| (kind.clone(), (*limit, Arc::new(Semaphore::new(*limit)))))
+            .collect();
+
+        Self {
+            max_concurrency: config.max_concurrency,
+            global: Arc::new(Semaphore::new(config.max_concurrency)),
+            kind_limits,
+            tenant_quotas: config.tenant_quotas.clone(),
+            active: Arc::new(Mutex::new(HashMap::new())),
+            tenants: Arc::new(Mutex::new(HashMap::new())),
+            released,
+        }
+    }
+
+    // Há ao menos uma vaga no limite global
+    pub fn has_capacity(&self) -> bool {
+        self.global.available_permits() > 0
+    }
+
+    // Tenta reservar uma vaga para o tipo e o tenant; o erro diz qual limite
+    // está cheio: o pool, o tipo ou a cota de concorrência do tenant
+    pub fn try_acquire(&self, kind: &str, tenant: &str) -> Result<WorkerPermit, Rejection> {
+        let global_permit = self
+            .global
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| Rejection::All)?;
+        let mut tenants = self.tenants.lock().unwrap();
+        let running = tenants.get(tenant).copied().unwrap_or(0);
+        if self
+            .tenant_quotas
+            .concurrency_limit(tenant)
+            .is_some_and(|limit| running >= limit)
+        {
+            return Err(Rejection::Tenant);
+        }
+        let kind_permit = match self.kind_limits.get(kind) {
+            Some((_, semaphore)) => Some(
+                semaphore
+                    .clone()
+                    .try_acquire_owned()
+                    .map_err(|_| Rejection::Kind)?,
+            ),
+            None => None,
+        };
+
+        *self
+            .active
+            .lock()
+            .unwrap()
+            .entry(kind.to_string())
+            .or_default() += 1;
+        tenants.insert(tenant.to_string(), running + 1);
+
+        Ok(WorkerPermit {
+            kind: kind.to_string(),
+            tenant: tenant.to_string(),
+            active: self.active.clone(),
+            tenants: self.tenants.clone(),
+            released: self.released.clone(),
+            global: Some(global_permit),
+            kind_permit,
+        })
+    }
+
+    // Tarefas do tenant em execução agora
+    pub fn tenant_active(&self, tenant: &str) -> usize {
+        self.tenants
+            .lock()
+            .unwrap()
+            .get(tenant)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn stats(&self) -> WorkerStats {
+        let active = self.active.lock().unwrap();
+
+        let mut kinds: BTreeMap<String, KindWorkerStats> = active
+            .iter()
+            .map(|(kind, count)| {
+                (
+                    kind.clone(),
+                    KindWorkerStats {
+                        max_concurrency: None,
+                        active: *count as u64,
+                    },
+                )
+            })
+            .collect();
+        for (kind, (limit, _)) in &self.kind_limits {
+            kinds.entry(kind.clone()).or_default().max_concurrency = Some(*limit as u64);
+        }
+
+        WorkerStats {
+            max_concurrency: self.max_concurrency as u64,
+            active: (self.max_concurrency - self.global.available_permits()) as u64,
+            kinds,
+            ..Default::default()
+        }
+    }
+}
+
+impl Drop for WorkerPermit {
+    fn drop(&mut self) {
+        for (counts, key) in [(&self.active, &self.kind), (&self.tenants, &self.tenant)] {
+            let mut counts = counts.lock().unwrap();
+            if let Some(count) = counts.get_mut(key) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    counts.remove(key);
+                }
+            }
+        }
+        // Devolver as vagas antes de acordar o despachante
+        self.kind_permit.take();
+        self.global.take();
+        self.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_and_kind_limits() {
+        let config = ProcessorConfig {
+            max_concurrency: 3,
+            kind_concurrency: HashMap::from([("lento".to_string(), 1)]),
+            ..Default::default()
+        };
+        let pool = WorkerPool::new(&config, Arc::new(Notify::new()));
+
+        let slow = pool.try_acquire("lento", "a").unwrap();
+        assert!(pool.try_acquire("lento", "a").is_err());
+
+        let a = pool.try_acquire("simulate", "a").unwrap();
+        let _b = pool.try_acquire("simulate", "a").unwrap();
+        assert!(pool.try_acquire("simulate", "a").is_err());
+
+        let stats = pool.stats();
+        assert_eq!(stats.active, 3);
+        assert_eq!(stats.kinds["lento"].max_concurrency, Some(1));
+        assert_eq!(stats.kinds["simulate"].active, 2);
+
+        drop(slow);
+        drop(a);
+        assert!(pool.try_acquire("lento", "a").is_ok());
+        assert_eq!(pool.stats().active, 1);
+    }
+
+    #[test]
+    fn test_tenant_concurrency_limits() {
+        let config = ProcessorConfig {
+            max_concurrency: 4,
+            tenant_quotas: TenantQuotas {
+                max_concurrency: Some(2),
+                concurrency_limits: HashMap::from([("pequeno".to_string(), 1)]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let pool = WorkerPool::new(&config, Arc::new(Notify::new()));
+
+        let small = pool.try_acquire("simulate", "pequeno").unwrap();
+        assert!(pool.try_acquire("simulate", "pequeno").is_err());
+        let a = pool.try_acquire("simulate", "a").unwrap();
+        let _b = pool.try_acquire("simulate", "a").unwrap();
+        assert!(pool.try_acquire("simulate", "a").is_err());
+        // Outro tenant ainda encontra vaga no pool
+        assert!(pool.try_acquire("simulate", "b").is_ok());
+        assert_eq!(pool.tenant_active("a"), 2);
+
+        drop(small);
+        drop(a);
+        assert!(pool.try_acquire("simulate", "pequeno").is_ok());
+        assert_eq!(pool.tenant_active("a"), 1);
+    }
+}
@@ -1,7 +1,5 @@
 use reqwest::Client;
 use serde_json::json;
-use std::time::Duration;
-use tokio::time::sleep;
 
 const BASE_URL: &str = "http://localhost:3000";
 
@@ -9,7 +7,7 @@ const BASE_URL: &str = "http://localhost:3000";
 async fn test_health_check() {
     let client = Client::new();
     let response = client
-        .get(&format!("{}/api/health", BASE_URL))
+        .get(format!("{}/api/health", BASE_URL))
         .send()
         .await
         .expect("Falha ao conectar ao servidor");
@@ -25,7 +23,7 @@ async fn test_create_and_list_tasks() {
 
     // Criar tarefa
     let create_response = client
-        .post(&format!("{}/api/tasks", BASE_URL))
+        .post(format!("{}/api/tasks", BASE_URL))
         .json(&json!({
             "name": "Tarefa de teste",
             "duration_ms": 1000,
@@ -41,7 +39,7 @@ async fn test_create_and_list_tasks() {
 
     // Listar tarefas
     let list_response = client
-        .get(&format!("{}/api/tasks", BASE_URL))
+        .get(format!("{}/api/tasks", BASE_URL))
         .send()
         .await
         .expect("Falha ao listar tarefas");
@@ -52,7 +50,7 @@ async fn test_create_and_list_tasks() {
 
     // Buscar tarefa específica
     let get_response = client
-        .get(&format!("{}/api/tasks/{}", BASE_URL, task_id))
+        .get(format!("{}/api/tasks/{}", BASE_URL, task_id))
         .send()
         .await
         .expect("Falha ao buscar tarefa");
@@ -67,7 +65,7 @@ async fn test_stats() {
     let client = Client::new();
 
     let response = client
-        .get(&format!("{}/api/stats", BASE_URL))
+        .get(format!("{}/api/stats", BASE_URL))
         .send()
         .await
         .expect("Falha ao obter estatísticas");